@@ -1,37 +1,679 @@
-use super::verify_exists;
-use crate::process::process_csv;
+use super::{verify_exists, verify_input};
+use crate::process::{
+    is_batch_input, load_column_types, parse_size, process_csv, process_csv_anonymize,
+    process_csv_batch, process_csv_cat, process_csv_decode, process_csv_dedup, process_csv_diff,
+    process_csv_fake, process_csv_from, process_csv_group, process_csv_join, process_csv_query,
+    process_csv_sample, process_csv_show, process_csv_split, process_csv_stats,
+    process_csv_validate, AnonymizeMethod, ConvertOptions, CsvReaderConfig, InputEncoding,
+    JoinKind, ParquetCompression, ParquetOptions, SampleMethod, SplitBy, TableStyle,
+};
 use crate::CmdExecuter;
-use clap::Parser;
+use clap::{ArgAction, ArgGroup, Parser};
+use enum_dispatch::enum_dispatch;
 use std::fmt;
+use std::path::Path;
 use std::{fmt::Display, str::FromStr};
 
 #[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecuter)]
+pub enum CsvSubCommand {
+    #[command(
+        about = "Convert JSON, YAML, NDJSON, Parquet, Arrow, MessagePack or CBOR records back to CSV"
+    )]
+    From(CsvFromOpts),
+    #[command(about = "Render a MessagePack or CBOR file as JSON")]
+    Decode(CsvDecodeOpts),
+    #[command(about = "Show a csv file as an aligned table")]
+    Show(CsvShowOpts),
+    #[command(about = "Run a SQL query over csv files, each file is a table named after it")]
+    Query(CsvQueryOpts),
+    #[command(about = "Profile every column of a csv file")]
+    Stats(CsvStatsOpts),
+    #[command(about = "Validate a csv file against a yaml schema")]
+    Validate(CsvValidateOpts),
+    #[command(about = "Join two csv files on key columns")]
+    Join(CsvJoinOpts),
+    #[command(about = "Show rows added, removed and changed between two csv files")]
+    Diff(CsvDiffOpts),
+    #[command(about = "Remove duplicate rows, keeping the first one")]
+    Dedup(CsvDedupOpts),
+    #[command(about = "Aggregate rows per group, optionally as a pivot table")]
+    Group(CsvGroupOpts),
+    #[command(about = "Replace the values of columns with keyed BLAKE3 tokens or masks")]
+    Anonymize(CsvAnonymizeOpts),
+    #[command(about = "Split a csv file into shards by rows, size or a column's value")]
+    Split(CsvSplitOpts),
+    #[command(about = "Pick a random sample of rows")]
+    Sample(CsvSampleOpts),
+    #[command(about = "Concatenate csv files, aligning columns by name")]
+    Cat(CsvCatOpts),
+    #[command(about = "Generate rows of fake data from a column spec")]
+    Fake(CsvFakeOpts),
+}
+
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct CsvOpts {
-    /// csv input file
-    #[arg(short, long, value_parser = verify_exists)]
+    // boxed, the csv subcommands would otherwise make every `SubCommand` large
+    #[command(subcommand)]
+    pub cmd: Option<Box<CsvSubCommand>>,
+    #[command(flatten)]
+    pub convert: Option<CsvConvertOpts>,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        match (self.cmd, self.convert) {
+            (Some(cmd), _) => (*cmd).execute().await,
+            (None, Some(convert)) => convert.run((&self.reader).into()).await,
+            (None, None) => anyhow::bail!("Either a csv subcommand or an input file is required"),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvReaderOpts {
+    /// delimiter, a single ascii character or `\t` for tab
+    #[arg(short, long, default_value_t = ',', value_parser = parse_delimiter)]
+    pub delimiter: char,
+    /// whether the first row is a header, use `--header false` for headerless files
+    #[arg(long, default_value_t = true, num_args = 0..=1, default_missing_value = "true", action = ArgAction::Set)]
+    pub header: bool,
+    /// input encoding like windows-1252, gbk or utf-16le, `auto` guesses it; a BOM always wins
+    #[arg(long, value_parser = parse_encoding, default_value = "utf-8")]
+    pub encoding: InputEncoding,
+}
+
+impl From<&CsvReaderOpts> for CsvReaderConfig {
+    fn from(opts: &CsvReaderOpts) -> Self {
+        Self {
+            delimiter: opts.delimiter as u8,
+            header: opts.header,
+            encoding: opts.encoding,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvConvertOpts {
+    /// csv input file, use `-` for stdin; a directory or glob pattern like `data/*.csv`
+    /// converts every file, several at a time
+    #[arg(short, long, value_parser = verify_input)]
     pub input: String,
-    /// csv output file
+    /// output file, use `-` for stdout; the output directory when converting several files
     #[arg(short, long)]
     pub output: Option<String>,
-    /// output format: json, yaml
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor, sqlite, parquet, arrow
     #[arg(long, value_parser = parse_format)]
     pub format: OutputFormat,
-    /// delimiter
-    #[arg(short, long, default_value_t = ',')]
+    /// infer integer, float, boolean, null and date columns instead of emitting strings
+    #[arg(long)]
+    pub infer_types: bool,
+    /// yaml file of `column: type` pairs forcing the type of some columns
+    #[arg(long, value_parser = verify_exists)]
+    pub types: Option<String>,
+    /// columns to keep, by name or 1-based index, comma separated
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+    /// keep rows matching a predicate like `Kit Number > 10`, repeat to combine
+    #[arg(long = "where")]
+    pub filters: Vec<String>,
+    /// sort by columns, comma separated, use `column:desc` for descending order
+    #[arg(long, value_delimiter = ',')]
+    pub sort_by: Vec<String>,
+    /// with sqlite output, add rows to an existing table instead of replacing it
+    #[arg(long)]
+    pub append: bool,
+    /// parquet compression: none, snappy, gzip, zstd
+    #[arg(long, value_parser = parse_compression, default_value = "snappy")]
+    pub compression: ParquetCompression,
+    /// maximum number of rows in a parquet row group
+    #[arg(
+        long,
+        default_value_t = ParquetOptions::default().row_group_size,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub row_group_size: usize,
+}
+
+impl CsvConvertOpts {
+    pub async fn run(self, config: CsvReaderConfig) -> anyhow::Result<()> {
+        let batch = is_batch_input(&self.input);
+        let output = match self.output {
+            Some(output) => output,
+            None if batch => "output".to_string(),
+            None => format!("output.{}", self.format),
+        };
+        let column_types = match self.types {
+            Some(path) => load_column_types(&path)?,
+            None => Default::default(),
+        };
+        let options = ConvertOptions {
+            infer_types: self.infer_types,
+            column_types,
+            select: self.select,
+            filters: self.filters,
+            sort_by: self.sort_by,
+            append: self.append,
+            parquet: ParquetOptions {
+                compression: self.compression,
+                row_group_size: self.row_group_size,
+            },
+        };
+        if !batch {
+            process_csv(&self.input, &output, self.format, config, &options)?;
+            return Ok(());
+        }
+
+        let items = process_csv_batch(&self.input, &output, self.format, config, options).await?;
+        for item in &items {
+            println!("{}", item);
+        }
+        let failed = items.iter().filter(|item| item.result.is_err()).count();
+        let rows = items
+            .iter()
+            .filter_map(|item| item.result.as_ref().ok())
+            .sum::<usize>();
+        println!(
+            "{} of {} files converted, {} rows",
+            items.len() - failed,
+            items.len(),
+            rows
+        );
+        if failed > 0 {
+            anyhow::bail!("{} of {} files failed", failed, items.len());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvFromOpts {
+    /// json, yaml, ndjson, parquet, arrow, msgpack or cbor input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// output file, use `-` for stdout; defaults to output.<format>
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// input format: json, yaml, ndjson, parquet, arrow, msgpack, cbor; detected from the file extension if omitted
+    #[arg(long, value_parser = parse_input_format)]
+    pub format: Option<InputFormat>,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub output_format: OutputFormat,
+    /// csv output delimiter, a single ascii character or `\t` for tab
+    #[arg(short, long, default_value_t = ',', value_parser = parse_delimiter)]
     pub delimiter: char,
-    /// header
-    #[arg(long, default_value_t = true)]
-    pub header: bool,
 }
 
-impl CmdExecuter for CsvOpts {
+impl CmdExecuter for CsvFromOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => InputFormat::from_path(&self.input)?,
+        };
+        let output = self
+            .output
+            .unwrap_or_else(|| format!("output.{}", self.output_format));
+        process_csv_from(
+            &self.input,
+            &output,
+            format,
+            self.output_format,
+            self.delimiter as u8,
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvDecodeOpts {
+    /// msgpack or cbor input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// json output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// input format: msgpack, cbor; detected from the file extension if omitted
+    #[arg(long, value_parser = parse_input_format)]
+    pub format: Option<InputFormat>,
+}
+
+impl CmdExecuter for CsvDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => InputFormat::from_path(&self.input)?,
+        };
+        process_csv_decode(&self.input, &self.output, format)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvShowOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+    /// show at most this many rows
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+    /// truncate cells wider than this
+    #[arg(long, default_value_t = 40)]
+    pub max_width: usize,
+    /// draw borders with ascii characters instead of unicode box drawing
+    #[arg(long)]
+    pub ascii: bool,
+}
+
+impl CmdExecuter for CsvShowOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let output = if let Some(output) = self.output {
-            output
-        } else {
-            format!("output.{}", self.format)
+        let style = TableStyle {
+            max_width: self.max_width,
+            ascii: self.ascii,
         };
-        process_csv(&self.input, output, self.format)
+        process_csv_show(&self.input, (&self.reader).into(), self.limit, style)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvQueryOpts {
+    /// SQL query, e.g. `SELECT Nationality, count(*) FROM juventus GROUP BY Nationality`
+    pub sql: String,
+    /// csv input files, repeat for several tables, use `-` for stdin (table `stdin`)
+    #[arg(short, long, required = true, value_parser = verify_exists)]
+    pub input: Vec<String>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvQueryOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_query(
+            &self.input,
+            &self.sql,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvStatsOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+    /// number of most frequent values to report per column
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+    /// print the profile as json instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl CmdExecuter for CsvStatsOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_stats(&self.input, (&self.reader).into(), self.top, self.json)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvValidateOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+    /// yaml schema with the constraints of each column
+    #[arg(long, value_parser = verify_exists)]
+    pub schema: String,
+}
+
+impl CmdExecuter for CsvValidateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_validate(&self.input, (&self.reader).into(), &self.schema)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvJoinOpts {
+    /// left csv file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub left: String,
+    /// right csv file, it is held in memory
+    #[arg(short, long, value_parser = verify_exists)]
+    pub right: String,
+    /// key columns present in both files, by name or 1-based index, comma separated
+    #[arg(long, required = true, value_delimiter = ',')]
+    pub on: Vec<String>,
+    /// join kind: inner, left, outer
+    #[arg(long, value_parser = parse_join_kind, default_value = "inner")]
+    pub kind: JoinKind,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvJoinOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_join(
+            &self.left,
+            &self.right,
+            &self.on,
+            self.kind,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvDiffOpts {
+    /// old version of the csv file
+    #[arg(long, value_parser = verify_exists)]
+    pub old: String,
+    /// new version of the csv file
+    #[arg(long, value_parser = verify_exists)]
+    pub new: String,
+    /// primary key columns, by name or 1-based index, comma separated
+    #[arg(long, required = true, value_delimiter = ',')]
+    pub key: Vec<String>,
+    /// print the changes as a json patch instead of a report
+    #[arg(long)]
+    pub json: bool,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvDiffOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_diff(
+            &self.old,
+            &self.new,
+            &self.key,
+            self.json,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvDedupOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// columns identifying a row, comma separated; the whole row if omitted
+    #[arg(long, value_delimiter = ',')]
+    pub key: Vec<String>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvDedupOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_dedup(
+            &self.input,
+            &self.key,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvAnonymizeOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// columns to anonymize, by name or 1-based index, comma separated
+    #[arg(long, required = true, value_delimiter = ',')]
+    pub columns: Vec<String>,
+    /// blake3 key file, the same value always maps to the same token under one key
+    #[arg(long, value_parser = verify_exists, required_unless_present = "mask")]
+    pub key: Option<String>,
+    /// partially redact values instead of hashing them
+    #[arg(long, conflicts_with = "key")]
+    pub mask: bool,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvAnonymizeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let method = match self.key {
+            Some(key) => AnonymizeMethod::Hash { key },
+            None => AnonymizeMethod::Mask,
+        };
+        process_csv_anonymize(
+            &self.input,
+            &self.columns,
+            &method,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvGroupOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// columns to group by, comma separated; all rows form one group if omitted
+    #[arg(long, value_delimiter = ',')]
+    pub by: Vec<String>,
+    /// aggregates like `count` or `avg:Kit Number`, comma separated:
+    /// count, sum, avg, min, max, distinct
+    #[arg(long, value_delimiter = ',')]
+    pub agg: Vec<String>,
+    /// turn the distinct values of this column into columns holding the (single) aggregate
+    #[arg(long)]
+    pub pivot: Option<String>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvGroupOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_group(
+            &self.input,
+            &self.by,
+            &self.agg,
+            self.pivot.as_deref(),
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("by").required(true).args(["rows", "bytes", "column"])))]
+pub struct CsvSplitOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// rows per shard
+    #[arg(long)]
+    pub rows: Option<usize>,
+    /// maximum shard size like `512K` or `10MB`, header included
+    #[arg(long, value_parser = parse_size)]
+    pub bytes: Option<u64>,
+    /// one shard per distinct value of this column
+    #[arg(long)]
+    pub column: Option<String>,
+    /// directory the shards are written to, named `<input>-0001.csv` or `<input>-<value>.csv`
+    #[arg(long, default_value = ".")]
+    pub out_dir: String,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvSplitOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let by = match (self.rows, self.bytes, self.column) {
+            (Some(rows), _, _) => SplitBy::Rows(rows),
+            (_, Some(bytes), _) => SplitBy::Bytes(bytes),
+            (_, _, Some(column)) => SplitBy::Column(column),
+            _ => unreachable!("clap requires one of --rows, --bytes and --column"),
+        };
+        let shards = process_csv_split(&self.input, &by, &self.out_dir, (&self.reader).into())?;
+        for shard in shards {
+            println!("{}", shard.display());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("method").required(true).args(["size", "fraction"])))]
+pub struct CsvSampleOpts {
+    /// csv input file, use `-` for stdin
+    #[arg(short, long, value_parser = verify_exists)]
+    pub input: String,
+    /// reservoir sampling of exactly this many rows
+    #[arg(short = 'n', long)]
+    pub size: Option<usize>,
+    /// uniform sampling keeping every row with this probability, between 0 and 1
+    #[arg(long)]
+    pub fraction: Option<f64>,
+    /// seed of the random generator, for a reproducible sample
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvSampleOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let method = match (self.size, self.fraction) {
+            (Some(size), _) => SampleMethod::Reservoir(size),
+            (_, Some(fraction)) => SampleMethod::Uniform(fraction),
+            _ => unreachable!("clap requires one of --size and --fraction"),
+        };
+        process_csv_sample(
+            &self.input,
+            method,
+            self.seed,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvCatOpts {
+    /// csv input files, one of them may be `-` for stdin
+    #[arg(required = true, value_parser = verify_exists)]
+    pub inputs: Vec<String>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor
+    #[arg(long, value_parser = parse_stream_format, default_value = "csv")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+impl CmdExecuter for CsvCatOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_cat(
+            &self.inputs,
+            &self.output,
+            self.format,
+            (&self.reader).into(),
+        )
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct CsvFakeOpts {
+    /// yaml spec with a generator `kind` per column, see `assets/juventus.fake.yaml`
+    #[arg(long, value_parser = verify_exists)]
+    pub spec: String,
+    /// number of rows to generate
+    #[arg(long, default_value_t = 100)]
+    pub rows: usize,
+    /// seed of the random generator, for reproducible rows
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// output file, use `-` for stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// output format: json, yaml, toml, csv, tsv, ndjson, markdown, html, latex, msgpack, cbor, parquet, arrow
+    #[arg(long, value_parser = parse_format, default_value = "csv")]
+    pub format: OutputFormat,
+}
+
+impl CmdExecuter for CsvFakeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_fake(&self.spec, self.rows, self.seed, &self.output, self.format)
+    }
+}
+
+fn parse_compression(s: &str) -> Result<ParquetCompression, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_join_kind(s: &str) -> Result<JoinKind, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_delimiter(s: &str) -> Result<char, anyhow::Error> {
+    match s {
+        "\\t" | "tab" => Ok('\t'),
+        s => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii() => Ok(c),
+                _ => anyhow::bail!("Delimiter must be a single ascii character: {}", s),
+            }
+        }
     }
 }
 
@@ -39,7 +681,18 @@ impl CmdExecuter for CsvOpts {
 pub enum OutputFormat {
     Json,
     Yaml,
-    // Toml,
+    Toml,
+    Csv,
+    Tsv,
+    Ndjson,
+    Sqlite,
+    Parquet,
+    Arrow,
+    Markdown,
+    Html,
+    Latex,
+    Msgpack,
+    Cbor,
 }
 
 impl Display for OutputFormat {
@@ -48,16 +701,41 @@ impl Display for OutputFormat {
     }
 }
 
+fn parse_encoding(s: &str) -> Result<InputEncoding, anyhow::Error> {
+    s.parse()
+}
+
 fn parse_format(s: &str) -> Result<OutputFormat, anyhow::Error> {
     s.parse()
 }
 
+/// Formats that don't need a typed schema or a database file
+fn parse_stream_format(s: &str) -> Result<OutputFormat, anyhow::Error> {
+    match s.parse()? {
+        format @ (OutputFormat::Sqlite | OutputFormat::Parquet | OutputFormat::Arrow) => {
+            anyhow::bail!("{} output is only supported by csv conversion", format)
+        }
+        format => Ok(format),
+    }
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(f: OutputFormat) -> Self {
         match f {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
-            // OutputFormat::Toml => "toml",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Sqlite => "sqlite",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Latex => "tex",
+            OutputFormat::Msgpack => "msgpack",
+            OutputFormat::Cbor => "cbor",
         }
     }
 }
@@ -68,8 +746,19 @@ impl TryFrom<&str> for OutputFormat {
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         match s.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
-            "yaml" => Ok(OutputFormat::Yaml),
-            // "toml" => Ok(OutputFormat::Toml),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "sqlite" | "sqlite3" | "db" => Ok(OutputFormat::Sqlite),
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(OutputFormat::Arrow),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "html" | "htm" => Ok(OutputFormat::Html),
+            "latex" | "tex" => Ok(OutputFormat::Latex),
+            "msgpack" | "messagepack" | "mp" => Ok(OutputFormat::Msgpack),
+            "cbor" => Ok(OutputFormat::Cbor),
             v => anyhow::bail!("Unsupported format: {}", v),
         }
     }
@@ -78,12 +767,72 @@ impl TryFrom<&str> for OutputFormat {
 impl FromStr for OutputFormat {
     type Err = anyhow::Error;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::try_from(s)
+    }
+}
+
+#[derive(Debug, Parser, Clone, Copy)]
+pub enum InputFormat {
+    Json,
+    Yaml,
+    Ndjson,
+    Parquet,
+    Arrow,
+    Msgpack,
+    Cbor,
+}
+
+impl InputFormat {
+    /// Guess the input format from the file extension
+    pub fn from_path(path: &str) -> anyhow::Result<Self> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Cannot detect input format of {}, use --format", path)
+            })?;
+        ext.parse()
+    }
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+fn parse_input_format(s: &str) -> Result<InputFormat, anyhow::Error> {
+    s.parse()
+}
+
+impl From<InputFormat> for &'static str {
+    fn from(f: InputFormat) -> Self {
+        match f {
+            InputFormat::Json => "json",
+            InputFormat::Yaml => "yaml",
+            InputFormat::Ndjson => "ndjson",
+            InputFormat::Parquet => "parquet",
+            InputFormat::Arrow => "arrow",
+            InputFormat::Msgpack => "msgpack",
+            InputFormat::Cbor => "cbor",
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "json" => Ok(OutputFormat::Json),
-            "yaml" => Ok(OutputFormat::Yaml),
-            // "toml" => Ok(OutputFormat::Toml),
-            v => anyhow::bail!("Unsupported format: {}", v),
+            "json" => Ok(InputFormat::Json),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            "parquet" => Ok(InputFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(InputFormat::Arrow),
+            "msgpack" | "messagepack" | "mp" => Ok(InputFormat::Msgpack),
+            "cbor" => Ok(InputFormat::Cbor),
+            v => anyhow::bail!("Unsupported input format: {}", v),
         }
     }
 }
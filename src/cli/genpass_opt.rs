@@ -1,43 +1,158 @@
-use crate::process::process_genpass;
+use crate::process::{
+    password_strength, process_genpass, process_passphrase, GenPassOptions, PassphraseOptions,
+    PasswordStrength, Wordlist,
+};
 use crate::CmdExecuter;
-use clap::Parser;
-use zxcvbn::zxcvbn;
+use clap::{ArgAction, Parser};
+use serde::Serialize;
+use std::io::Write;
 
 #[derive(Parser, Debug)]
 pub struct GenPassOpts {
     /// Length of the password
     #[arg(long, default_value = "16")]
     pub length: usize,
-    /// Uppercase letters
-    #[arg(short, long, default_value_t = true)]
+    /// Leave out uppercase letters
+    #[arg(long = "no-uppercase", action = ArgAction::SetFalse)]
     pub uppercase: bool,
-    /// Lowercase letters
-    #[arg(short, long, default_value_t = true)]
+    /// Leave out lowercase letters
+    #[arg(long = "no-lowercase", action = ArgAction::SetFalse)]
     pub lowercase: bool,
-    /// Numbers
-    #[arg(long, default_value_t = true)]
+    /// Leave out numbers
+    #[arg(long = "no-number", action = ArgAction::SetFalse)]
     pub number: bool,
-    /// Symbols
-    #[arg(long, default_value_t = true)]
+    /// Leave out symbols
+    #[arg(long = "no-symbol", action = ArgAction::SetFalse)]
     pub symbol: bool,
+    /// Draw from exactly these characters instead of the classes above
+    #[arg(long)]
+    pub charset: Option<String>,
+    /// Never use these characters
+    #[arg(long, default_value = "")]
+    pub exclude: String,
+    /// Leave out characters that are easily confused, like O and 0 or l and 1
+    #[arg(long)]
+    pub exclude_ambiguous: bool,
+    /// Least number of uppercase letters
+    #[arg(long)]
+    pub min_upper: Option<usize>,
+    /// Least number of lowercase letters
+    #[arg(long)]
+    pub min_lower: Option<usize>,
+    /// Least number of digits
+    #[arg(long)]
+    pub min_digits: Option<usize>,
+    /// Least number of symbols
+    #[arg(long)]
+    pub min_symbols: Option<usize>,
+    /// Generate a passphrase of this many words instead of a password
+    #[arg(long)]
+    pub words: Option<usize>,
+    /// Passphrase wordlist, one word per line or in EFF dice format
+    #[arg(long, default_value = "assets/eff_large_wordlist.txt")]
+    pub wordlist: String,
+    /// Passphrase word separator
+    #[arg(long, default_value = "-")]
+    pub separator: String,
+    /// Capitalize every passphrase word
+    #[arg(long)]
+    pub capitalize: bool,
+    /// Append a random digit to one passphrase word
+    #[arg(long)]
+    pub insert_digit: bool,
+    /// Append a random symbol to one passphrase word
+    #[arg(long)]
+    pub insert_symbol: bool,
+    /// Number of passwords to generate, at most 100000
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=100_000)
+    )]
+    pub count: usize,
+    /// Print the passwords with their zxcvbn strength estimates as json
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// One generated password with its strength, as printed by `--json`
+#[derive(Debug, Serialize)]
+struct GeneratedPassword {
+    password: String,
+    /// Passphrases only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entropy: Option<f64>,
+    #[serde(flatten)]
+    strength: PasswordStrength,
 }
 
 impl CmdExecuter for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let password = process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.number,
-            self.symbol,
-        )?;
-        // Make sure the password have at least one of each type
+        let mut generated = Vec::with_capacity(self.count);
+        if let Some(words) = self.words {
+            let wordlist = Wordlist::load(&self.wordlist)?;
+            let options = PassphraseOptions {
+                words,
+                separator: self.separator,
+                capitalize: self.capitalize,
+                digit: self.insert_digit,
+                symbol: self.insert_symbol,
+            };
+            let mut rng = rand::thread_rng();
+            for _ in 0..self.count {
+                let passphrase = process_passphrase(&wordlist, &options, &mut rng)?;
+                generated.push((passphrase.phrase, Some(passphrase.entropy)));
+            }
+        } else {
+            let options = GenPassOptions {
+                length: self.length,
+                uppercase: self.uppercase,
+                lowercase: self.lowercase,
+                number: self.number,
+                symbol: self.symbol,
+                charset: self.charset,
+                exclude: self.exclude,
+                exclude_ambiguous: self.exclude_ambiguous,
+                min_upper: self.min_upper,
+                min_lower: self.min_lower,
+                min_digits: self.min_digits,
+                min_symbols: self.min_symbols,
+            };
+            for _ in 0..self.count {
+                let password = String::from_utf8(process_genpass(&options)?)?;
+                generated.push((password, None));
+            }
+        }
 
-        let passwords_string = String::from_utf8(password)?;
-        println!("{}", passwords_string);
+        if self.json {
+            let passwords = generated
+                .into_iter()
+                .map(|(password, entropy)| {
+                    let strength = password_strength(&password)?;
+                    Ok(GeneratedPassword {
+                        password,
+                        entropy,
+                        strength,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &passwords)?;
+            writeln!(stdout)?;
+            return Ok(());
+        }
 
-        let estimate = zxcvbn(&passwords_string, &[]).unwrap();
-        eprintln!("password strength {}", estimate.score());
+        for (password, entropy) in generated {
+            println!("{}", password);
+            let strength = password_strength(&password)?;
+            match entropy {
+                Some(entropy) => eprintln!(
+                    "passphrase entropy {:.1} bits, strength {}",
+                    entropy, strength.score
+                ),
+                None => eprintln!("password strength {}", strength.score),
+            }
+        }
         Ok(())
     }
 }
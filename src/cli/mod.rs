@@ -1,6 +1,6 @@
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod base64_opt;
 mod chacha20poly1305;
@@ -14,7 +14,12 @@ pub use base64_opt::{Base64DecodeOpts, Base64EncodeOpts, Base64Format, Base64Sub
 pub use chacha20poly1305::{
     Chacha20Poly1305SubCommand, TextChacCha20GenKeyOpts, TextDecryptOpts, TextEncryptOpts,
 };
-pub use csv_opt::{CsvOpts, OutputFormat};
+pub use csv_opt::{
+    CsvAnonymizeOpts, CsvCatOpts, CsvConvertOpts, CsvDecodeOpts, CsvDedupOpts, CsvDiffOpts,
+    CsvFakeOpts, CsvFromOpts, CsvGroupOpts, CsvJoinOpts, CsvOpts, CsvQueryOpts, CsvReaderOpts,
+    CsvSampleOpts, CsvShowOpts, CsvSplitOpts, CsvStatsOpts, CsvSubCommand, CsvValidateOpts,
+    InputFormat, OutputFormat,
+};
 pub use genpass_opt::GenPassOpts;
 pub use http::{HttpServeOpts, HttpSubCommand};
 pub use jwt::{JwtSignOpts, JwtSubCommand, JwtVerifyOpts};
@@ -54,6 +59,16 @@ fn verify_exists(filename: &str) -> Result<String, String> {
     }
 }
 
+/// Like `verify_exists`, but glob patterns are matched later. A file whose name has
+/// glob characters is taken literally.
+fn verify_input(input: &str) -> Result<String, String> {
+    if input.contains(['*', '?', '[']) && !Path::new(input).exists() {
+        Ok(input.into())
+    } else {
+        verify_exists(input)
+    }
+}
+
 fn verify_path_exists(path: &str) -> Result<PathBuf, &'static str> {
     let path = PathBuf::from(path);
     if path.exists() && path.is_dir() {
@@ -76,5 +91,8 @@ mod tests {
             Err("File does not exist".into())
         );
         assert_eq!(verify_exists("Cargo.toml"), Ok("Cargo.toml".into()));
+        assert_eq!(verify_input("assets/*.csv"), Ok("assets/*.csv".into()));
+        assert_eq!(verify_input("assets"), Ok("assets".into()));
+        assert!(verify_input("not_exist.csv").is_err());
     }
 }
@@ -0,0 +1,163 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::resolve_columns;
+use crate::process::csv_query::unique_names;
+use crate::process::csv_writer::{string_record, RecordWriter};
+use crate::process::text::{Blake3, TextLoader};
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use csv::Reader;
+use std::io::{BufWriter, Read, Write};
+
+/// How the values of the anonymized columns are replaced
+#[derive(Debug, Clone)]
+pub enum AnonymizeMethod {
+    /// keyed blake3 digest, the key is read from this file
+    Hash { key: String },
+    /// keep the first and last character, star out the letters and digits in between
+    Mask,
+}
+
+enum Anonymizer {
+    Hash(Blake3),
+    Mask,
+}
+
+impl Anonymizer {
+    fn load(method: &AnonymizeMethod) -> anyhow::Result<Self> {
+        Ok(match method {
+            AnonymizeMethod::Hash { key } => Anonymizer::Hash(Blake3::load(key)?),
+            AnonymizeMethod::Mask => Anonymizer::Mask,
+        })
+    }
+
+    /// Empty cells stay empty so missing values remain recognizable
+    fn apply(&self, value: &str) -> String {
+        if value.is_empty() {
+            return String::new();
+        }
+        match self {
+            Anonymizer::Hash(blake3) => {
+                URL_SAFE_NO_PAD.encode(blake3.hash(value.as_bytes()).as_bytes())
+            }
+            Anonymizer::Mask => mask(value),
+        }
+    }
+}
+
+pub fn process_csv_anonymize(
+    input: &str,
+    columns: &[String],
+    method: &AnonymizeMethod,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let anonymizer = Anonymizer::load(method)?;
+    let output = BufWriter::new(get_writer(output)?);
+    anonymize_records(
+        config.open(input)?,
+        columns,
+        &anonymizer,
+        config,
+        output,
+        format,
+    )
+}
+
+fn anonymize_records<R: Read, W: Write + Send>(
+    mut reader: Reader<R>,
+    columns: &[String],
+    anonymizer: &Anonymizer,
+    config: CsvReaderConfig,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let header = config.headers(&mut reader)?;
+    let columns = resolve_columns(&header, columns)?;
+    let headers = unique_names(header.iter());
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    for record in reader.records() {
+        let record = record?;
+        let cells = record
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if columns.contains(&i) {
+                    anonymizer.apply(cell)
+                } else {
+                    cell.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        writer.write(&string_record(
+            &headers,
+            cells.iter().map(|cell| Some(cell.as_str())),
+        ))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Values of up to two characters are starred out completely
+fn mask(value: &str) -> String {
+    let chars = value.chars().collect::<Vec<_>>();
+    let last = chars.len() - 1;
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let kept = chars.len() > 2 && (i == 0 || i == last);
+            if kept || !c.is_alphanumeric() {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anonymize(method: AnonymizeMethod) -> anyhow::Result<String> {
+        let data = "Name,Kit Number\nMattia Perin,37\nMattia Perin,1\n,2\n";
+        let config = CsvReaderConfig::default();
+        let mut out = Vec::new();
+        anonymize_records(
+            config.reader(data.as_bytes()),
+            &["Name".to_string()],
+            &Anonymizer::load(&method)?,
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_anonymize_hash() -> anyhow::Result<()> {
+        let out = anonymize(AnonymizeMethod::Hash {
+            key: "fixture/blake3.txt".to_string(),
+        })?;
+        let rows = out.lines().collect::<Vec<_>>();
+        let token = rows[1].strip_suffix(",37").unwrap();
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, "Mattia Perin");
+        assert_eq!(rows[2], format!("{},1", token));
+        assert_eq!(rows[3], ",2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymize_mask() -> anyhow::Result<()> {
+        assert_eq!(
+            anonymize(AnonymizeMethod::Mask)?,
+            "Name,Kit Number\nM***** ****n,37\nM***** ****n,1\n,2\n"
+        );
+        assert_eq!(mask("Apr 18, 1990"), "A** **, ***0");
+        assert_eq!(mask("ab"), "**");
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use crate::process::csv_types::{parse_date, ColumnType};
+use crate::{InputFormat, OutputFormat};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, RecordBatchReader,
+    StringArray, TimestampMicrosecondArray,
+};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::{WriterProperties, DEFAULT_MAX_ROW_GROUP_SIZE};
+use serde_json::{Map, Value};
+use std::io::{Cursor, Read, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Rows collected before they are turned into a record batch
+const BATCH_SIZE: usize = 8192;
+
+/// How parquet output is laid out
+#[derive(Debug, Clone, Copy)]
+pub struct ParquetOptions {
+    pub compression: ParquetCompression,
+    /// Maximum number of rows in a row group
+    pub row_group_size: usize,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self {
+            compression: ParquetCompression::Snappy,
+            row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCompression {
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+impl FromStr for ParquetCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "uncompressed" => Ok(ParquetCompression::None),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip),
+            "zstd" => Ok(ParquetCompression::Zstd),
+            v => anyhow::bail!("Unsupported compression: {}", v),
+        }
+    }
+}
+
+impl From<ParquetCompression> for Compression {
+    fn from(c: ParquetCompression) -> Self {
+        match c {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        }
+    }
+}
+
+/// Writes records into parquet or arrow ipc files a batch at a time
+pub(crate) struct ColumnarWriter<W: Write> {
+    schema: SchemaRef,
+    headers: Vec<String>,
+    types: Vec<ColumnType>,
+    rows: Vec<Value>,
+    sink: BatchSink<W>,
+}
+
+enum BatchSink<W: Write> {
+    Parquet(ArrowWriter<W>),
+    Ipc(arrow_ipc::writer::FileWriter<W>),
+}
+
+impl<W: Write + Send> ColumnarWriter<W> {
+    pub fn new(
+        writer: W,
+        format: OutputFormat,
+        headers: Vec<String>,
+        types: &[ColumnType],
+        parquet: ParquetOptions,
+    ) -> anyhow::Result<Self> {
+        let fields = headers
+            .iter()
+            .zip(types)
+            .map(|(name, ty)| Field::new(name, data_type(*ty), true))
+            .collect::<Vec<_>>();
+        let schema = Arc::new(Schema::new(fields));
+        let sink = match format {
+            OutputFormat::Parquet => {
+                if parquet.row_group_size == 0 {
+                    anyhow::bail!("The parquet row group size must be at least 1");
+                }
+                let props = WriterProperties::builder()
+                    .set_compression(parquet.compression.into())
+                    .set_max_row_group_size(parquet.row_group_size)
+                    .build();
+                BatchSink::Parquet(ArrowWriter::try_new(writer, schema.clone(), Some(props))?)
+            }
+            OutputFormat::Arrow => {
+                BatchSink::Ipc(arrow_ipc::writer::FileWriter::try_new(writer, &schema)?)
+            }
+            format => anyhow::bail!("{} is not a columnar format", format),
+        };
+        Ok(Self {
+            schema,
+            headers,
+            types: types.to_vec(),
+            rows: Vec::with_capacity(BATCH_SIZE),
+            sink,
+        })
+    }
+
+    pub fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        self.rows.push(record.clone());
+        if self.rows.len() >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        self.flush_batch()?;
+        Ok(match self.sink {
+            BatchSink::Parquet(writer) => writer.into_inner()?,
+            BatchSink::Ipc(writer) => writer.into_inner()?,
+        })
+    }
+
+    fn flush_batch(&mut self) -> anyhow::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let columns = self
+            .headers
+            .iter()
+            .zip(&self.types)
+            .map(|(name, ty)| column_array(&self.rows, name, *ty))
+            .collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        match &mut self.sink {
+            BatchSink::Parquet(writer) => writer.write(&batch)?,
+            BatchSink::Ipc(writer) => writer.write(&batch)?,
+        }
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+/// Arrow type of a column, dates become timestamps and all-null columns strings
+fn data_type(ty: ColumnType) -> DataType {
+    match ty {
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Float => DataType::Float64,
+        ColumnType::Date => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ColumnType::Null | ColumnType::String => DataType::Utf8,
+    }
+}
+
+fn column_array(rows: &[Value], name: &str, ty: ColumnType) -> ArrayRef {
+    let cells = rows.iter().map(|row| &row[name]);
+    match ty {
+        ColumnType::Boolean => Arc::new(cells.map(Value::as_bool).collect::<BooleanArray>()),
+        ColumnType::Integer => Arc::new(cells.map(Value::as_i64).collect::<Int64Array>()),
+        ColumnType::Float => Arc::new(cells.map(Value::as_f64).collect::<Float64Array>()),
+        ColumnType::Date => Arc::new(
+            cells
+                .map(|v| v.as_str().and_then(parse_date))
+                .map(|d| d.map(|d| d.and_utc().timestamp_micros()))
+                .collect::<TimestampMicrosecondArray>(),
+        ),
+        ColumnType::Null | ColumnType::String => Arc::new(
+            cells
+                .map(|v| match v {
+                    Value::Null => None,
+                    Value::String(s) => Some(s.clone()),
+                    v => Some(v.to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Read a parquet or arrow ipc file into json objects keyed by column name
+pub fn read_columnar(mut reader: impl Read, format: InputFormat) -> anyhow::Result<Vec<Value>> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let batches: Box<dyn RecordBatchReader> = match format {
+        InputFormat::Parquet => {
+            Box::new(ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(data))?.build()?)
+        }
+        InputFormat::Arrow => Box::new(arrow_ipc::reader::FileReader::try_new(
+            Cursor::new(data),
+            None,
+        )?),
+        format => anyhow::bail!("{} is not a columnar format", format),
+    };
+
+    let mut records = vec![];
+    for batch in batches {
+        let batch = batch?;
+        let schema = batch.schema();
+        let mut rows = vec![Map::new(); batch.num_rows()];
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            for (row, value) in rows.iter_mut().zip(column_values(column)?) {
+                row.insert(field.name().clone(), value);
+            }
+        }
+        records.extend(rows.into_iter().map(Value::Object));
+    }
+    Ok(records)
+}
+
+/// Json values of an arrow column, anything but booleans and numbers is formatted as text
+fn column_values(column: &ArrayRef) -> anyhow::Result<Vec<Value>> {
+    let values = match column.data_type() {
+        DataType::Boolean => column_cast::<BooleanArray>(column, &DataType::Boolean)?
+            .iter()
+            .map(|v| v.map_or(Value::Null, Value::from))
+            .collect(),
+        t if t.is_integer() => column_cast::<Int64Array>(column, &DataType::Int64)?
+            .iter()
+            .map(|v| v.map_or(Value::Null, Value::from))
+            .collect(),
+        t if t.is_floating() => column_cast::<Float64Array>(column, &DataType::Float64)?
+            .iter()
+            .map(|v| v.map_or(Value::Null, Value::from))
+            .collect(),
+        _ => {
+            let formatter = ArrayFormatter::try_new(column.as_ref(), &FormatOptions::default())?;
+            (0..column.len())
+                .map(|i| {
+                    if column.is_null(i) {
+                        Value::Null
+                    } else {
+                        Value::String(formatter.value(i).to_string())
+                    }
+                })
+                .collect()
+        }
+    };
+    Ok(values)
+}
+
+fn column_cast<T: Array + Clone + 'static>(column: &ArrayRef, to: &DataType) -> anyhow::Result<T> {
+    let cast = arrow_cast::cast(column, to)?;
+    let array = cast
+        .as_any()
+        .downcast_ref::<T>()
+        .ok_or_else(|| anyhow::anyhow!("Cannot read column as {}", to))?;
+    Ok(array.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(format: OutputFormat, input: InputFormat) -> anyhow::Result<Vec<Value>> {
+        let headers = vec![
+            "Name".to_string(),
+            "Kit Number".to_string(),
+            "DOB".to_string(),
+        ];
+        let types = [ColumnType::String, ColumnType::Integer, ColumnType::Date];
+        let options = ParquetOptions {
+            compression: ParquetCompression::Zstd,
+            row_group_size: 1,
+        };
+        let mut writer = ColumnarWriter::new(Vec::new(), format, headers, &types, options)?;
+        writer.write(&json!({"Name": "Buffon", "Kit Number": 77, "DOB": "1978-01-28"}))?;
+        writer.write(&json!({"Name": "Perin", "Kit Number": null, "DOB": null}))?;
+        let data = writer.finish()?;
+        read_columnar(data.as_slice(), input)
+    }
+
+    #[test]
+    fn test_columnar_round_trip() -> anyhow::Result<()> {
+        let expected = vec![
+            json!({"Name": "Buffon", "Kit Number": 77, "DOB": "1978-01-28T00:00:00"}),
+            json!({"Name": "Perin", "Kit Number": null, "DOB": null}),
+        ];
+        assert_eq!(
+            round_trip(OutputFormat::Parquet, InputFormat::Parquet)?,
+            expected
+        );
+        assert_eq!(
+            round_trip(OutputFormat::Arrow, InputFormat::Arrow)?,
+            expected
+        );
+
+        let options = ParquetOptions {
+            row_group_size: 0,
+            ..Default::default()
+        };
+        let writer = ColumnarWriter::new(
+            Vec::new(),
+            OutputFormat::Parquet,
+            vec!["Name".to_string()],
+            &[ColumnType::String],
+            options,
+        );
+        assert!(writer.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use crate::process::csv_convert::{process_csv, ConvertOptions, CsvReaderConfig};
+use crate::OutputFormat;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+/// Outcome of converting one file of a batch
+#[derive(Debug)]
+pub struct BatchItem {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// Number of records written
+    pub result: anyhow::Result<usize>,
+}
+
+impl fmt::Display for BatchItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}: ", self.input.display(), self.output.display())?;
+        match &self.result {
+            Ok(rows) => write!(f, "{} rows", rows),
+            Err(e) => write!(f, "failed: {:#}", e),
+        }
+    }
+}
+
+/// Whether the input names several files, a directory or a glob pattern.
+///
+/// A file whose name happens to have glob characters is a single file.
+pub fn is_batch_input(input: &str) -> bool {
+    let path = Path::new(input);
+    path.is_dir() || (!path.exists() && input.contains(['*', '?', '[']))
+}
+
+/// Convert every csv file of a directory (recursively) or matching a glob pattern,
+/// several at a time.
+///
+/// Outputs mirror the inputs' paths below the directory, or below the part of the
+/// pattern without wildcards, inside `out_dir`. A failed file doesn't stop the others.
+pub async fn process_csv_batch(
+    input: &str,
+    out_dir: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+    options: ConvertOptions,
+) -> anyhow::Result<Vec<BatchItem>> {
+    if out_dir == "-" {
+        anyhow::bail!("Converting several files needs an output directory");
+    }
+    let (base, inputs) = batch_inputs(input)?;
+    let extension = format.to_string();
+    let base_dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+    if extension == "csv" && same_file(base_dir, Path::new(out_dir)) {
+        anyhow::bail!(
+            "The output directory {} is the input directory, csv outputs would overwrite the inputs",
+            out_dir
+        );
+    }
+    let options = Arc::new(options);
+    let limit = std::thread::available_parallelism().map_or(4, |n| n.get());
+
+    let mut tasks = JoinSet::new();
+    let mut items = vec![];
+    for (i, input) in inputs.into_iter().enumerate() {
+        if tasks.len() >= limit {
+            items.push(join_next(&mut tasks).await?);
+        }
+        let output = output_path(&base, &input, out_dir, format);
+        if same_file(&input, &output) {
+            let result = Err(anyhow::anyhow!("The output would overwrite the input"));
+            items.push((
+                i,
+                BatchItem {
+                    input,
+                    output,
+                    result,
+                },
+            ));
+            continue;
+        }
+        let options = options.clone();
+        tasks.spawn_blocking(move || {
+            let result = convert_file(&input, &output, format, config, &options);
+            (
+                i,
+                BatchItem {
+                    input,
+                    output,
+                    result,
+                },
+            )
+        });
+    }
+    while !tasks.is_empty() {
+        items.push(join_next(&mut tasks).await?);
+    }
+    items.sort_by_key(|(i, _)| *i);
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+async fn join_next<T: 'static>(tasks: &mut JoinSet<T>) -> anyhow::Result<T> {
+    match tasks.join_next().await {
+        Some(result) => Ok(result?),
+        None => anyhow::bail!("No conversion left to wait for"),
+    }
+}
+
+fn convert_file(
+    input: &Path,
+    output: &Path,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+    options: &ConvertOptions,
+) -> anyhow::Result<usize> {
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let path = |p: &Path| {
+        p.to_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("Path is not valid utf-8: {}", p.display()))
+    };
+    process_csv(&path(input)?, &path(output)?, format, config, options)
+}
+
+/// The directory outputs are mirrored from and the csv files to convert, sorted
+fn batch_inputs(input: &str) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
+    let (base, pattern) = if Path::new(input).is_dir() {
+        let base = PathBuf::from(input);
+        let pattern = base.join("**").join("*.csv");
+        (base, pattern.to_string_lossy().to_string())
+    } else {
+        (pattern_base(input), input.to_string())
+    };
+    let mut inputs = glob::glob(&pattern)?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    if inputs.is_empty() {
+        anyhow::bail!("No csv files match {}", input);
+    }
+    inputs.sort();
+    Ok((base, inputs))
+}
+
+/// Leading directories of a glob pattern that contain no wildcards
+fn pattern_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    let components = Path::new(pattern).components().collect::<Vec<_>>();
+    // the last component is always a file name (or pattern)
+    for component in &components[..components.len().saturating_sub(1)] {
+        match component {
+            Component::Normal(name) if name.to_string_lossy().contains(['*', '?', '[']) => break,
+            component => base.push(component),
+        }
+    }
+    base
+}
+
+/// Whether both paths exist and are the same file or directory
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn output_path(base: &Path, input: &Path, out_dir: &str, format: OutputFormat) -> PathBuf {
+    let relative = input.strip_prefix(base).unwrap_or(input);
+    Path::new(out_dir)
+        .join(relative)
+        .with_extension(format.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_paths() {
+        assert_eq!(
+            pattern_base("data/2024/*/day.csv"),
+            PathBuf::from("data/2024")
+        );
+        assert_eq!(pattern_base("*.csv"), PathBuf::new());
+        assert_eq!(
+            output_path(
+                Path::new("data"),
+                Path::new("data/01/day.csv"),
+                "out",
+                OutputFormat::Json
+            ),
+            PathBuf::from("out/01/day.json")
+        );
+    }
+
+    #[test]
+    fn test_is_batch_input() -> anyhow::Result<()> {
+        assert!(is_batch_input("assets/*.csv"));
+        assert!(is_batch_input("assets"));
+        assert!(!is_batch_input("assets/juventus.csv"));
+        let literal = std::env::temp_dir().join(format!("rcli-r[{}].csv", std::process::id()));
+        std::fs::write(&literal, "a\n1\n")?;
+        assert!(!is_batch_input(&literal.to_string_lossy()));
+        std::fs::remove_file(&literal)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_csv_batch() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-batch-{}", std::process::id()));
+        let input = dir.join("input");
+        std::fs::create_dir_all(input.join("nested"))?;
+        std::fs::copy("assets/juventus.csv", input.join("juventus.csv"))?;
+        std::fs::write(input.join("nested/small.csv"), "a,b\n1,2\n")?;
+        std::fs::write(input.join("nested/broken.csv"), "a,b\n1,2,3\n")?;
+        let out = dir.join("out");
+
+        let items = process_csv_batch(
+            &input.to_string_lossy(),
+            &out.to_string_lossy(),
+            OutputFormat::Ndjson,
+            CsvReaderConfig::default(),
+            ConvertOptions::default(),
+        )
+        .await?;
+        let summary = items
+            .iter()
+            .map(|item| {
+                (
+                    item.input.strip_prefix(&input).unwrap(),
+                    item.result.as_ref().ok(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Path::new("juventus.csv"), Some(&27)),
+                (Path::new("nested/broken.csv"), None),
+                (Path::new("nested/small.csv"), Some(&1)),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(out.join("nested/small.ndjson"))?,
+            "{\"a\":\"1\",\"b\":\"2\"}\n"
+        );
+
+        // converting into the input directory would truncate the inputs while reading them
+        let result = process_csv_batch(
+            &input.to_string_lossy(),
+            &input.to_string_lossy(),
+            OutputFormat::Csv,
+            CsvReaderConfig::default(),
+            ConvertOptions::default(),
+        )
+        .await;
+        assert!(result.is_err());
+        let items = process_csv_batch(
+            &input.join("*.csv").to_string_lossy(),
+            &dir.join("input/../input").to_string_lossy(),
+            OutputFormat::Csv,
+            CsvReaderConfig::default(),
+            ConvertOptions::default(),
+        )
+        .await;
+        assert!(items.is_err());
+        assert_eq!(
+            std::fs::read_to_string(input.join("nested/small.csv"))?,
+            "a,b\n1,2\n"
+        );
+        std::fs::write(input.join("small.tsv"), "a,b\n1,2\n")?;
+        let items = process_csv_batch(
+            &input.join("*.tsv").to_string_lossy(),
+            &input.to_string_lossy(),
+            OutputFormat::Tsv,
+            CsvReaderConfig::default(),
+            ConvertOptions::default(),
+        )
+        .await?;
+        assert!(items[0].result.is_err());
+        assert_eq!(
+            std::fs::read_to_string(input.join("small.tsv"))?,
+            "a,b\n1,2\n"
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::utils::{get_data, get_writer};
+use crate::{InputFormat, OutputFormat};
+use serde_json::Value;
+use std::io::{Cursor, Read, Write};
+
+/// Cbor header of an array whose length is only known once the break byte is written
+const CBOR_ARRAY_START: u8 = 0x9f;
+const CBOR_BREAK: u8 = 0xff;
+
+/// Render a messagepack or cbor file as pretty json
+pub fn process_csv_decode(input: &str, output: &str, format: InputFormat) -> anyhow::Result<()> {
+    let value = decode_binary(get_data(input)?, format)?;
+    let mut writer = get_writer(output)?;
+    serde_json::to_writer_pretty(&mut writer, &value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decode a cbor value, or a messagepack stream of records.
+///
+/// A messagepack stream holding a single array is that array, so files with all the
+/// records in one array read the same as a stream of them.
+pub fn decode_binary(mut reader: impl Read, format: InputFormat) -> anyhow::Result<Value> {
+    match format {
+        InputFormat::Msgpack => {
+            let mut data = vec![];
+            reader.read_to_end(&mut data)?;
+            let mut cursor = Cursor::new(data.as_slice());
+            let mut values = vec![];
+            while (cursor.position() as usize) < data.len() {
+                values.push(rmp_serde::from_read(&mut cursor)?);
+            }
+            match values.as_slice() {
+                [Value::Array(_)] => Ok(values.remove(0)),
+                _ => Ok(Value::Array(values)),
+            }
+        }
+        InputFormat::Cbor => Ok(ciborium::from_reader(reader)?),
+        format => anyhow::bail!("{} is not a binary format", format),
+    }
+}
+
+/// Start of the array holding the records. Cbor streams an indefinite length array,
+/// messagepack records are written one after the other with nothing around them
+pub(crate) fn start_array(writer: &mut impl Write, format: OutputFormat) -> anyhow::Result<()> {
+    if let OutputFormat::Cbor = format {
+        writer.write_all(&[CBOR_ARRAY_START])?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_value(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    value: &Value,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Msgpack => rmp_serde::encode::write(writer, value)?,
+        OutputFormat::Cbor => ciborium::into_writer(value, writer)?,
+        format => anyhow::bail!("{} is not a binary format", format),
+    }
+    Ok(())
+}
+
+/// Close the array started by `start_array`
+pub(crate) fn finish_array(writer: &mut impl Write, format: OutputFormat) -> anyhow::Result<()> {
+    if let OutputFormat::Cbor = format {
+        writer.write_all(&[CBOR_BREAK])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::csv_writer::serialize_records;
+    use serde_json::json;
+
+    #[test]
+    fn test_binary_round_trip() -> anyhow::Result<()> {
+        let headers = vec![
+            "Name".to_string(),
+            "Kit Number".to_string(),
+            "Caps".to_string(),
+        ];
+        let records = vec![
+            json!({"Name": "Buffon", "Kit Number": 77, "Caps": 1.5}),
+            json!({"Name": "Perin", "Kit Number": null, "Caps": [true]}),
+        ];
+        let data = serialize_records(&headers, &records, OutputFormat::Msgpack)?;
+        // a stream of maps, not an array
+        assert_eq!(data[0], 0x83);
+        assert_eq!(
+            decode_binary(data.as_slice(), InputFormat::Msgpack)?,
+            json!(records)
+        );
+        let array = rmp_serde::to_vec(&records)?;
+        assert_eq!(
+            decode_binary(array.as_slice(), InputFormat::Msgpack)?,
+            json!(records)
+        );
+        let one = serialize_records(&headers, &records[..1], OutputFormat::Msgpack)?;
+        assert_eq!(
+            decode_binary(one.as_slice(), InputFormat::Msgpack)?,
+            json!(&records[..1])
+        );
+        assert_eq!(decode_binary(&[][..], InputFormat::Msgpack)?, json!([]));
+
+        let data = serialize_records(&headers, &records, OutputFormat::Cbor)?;
+        assert_eq!(
+            decode_binary(data.as_slice(), InputFormat::Cbor)?,
+            json!(records)
+        );
+
+        let empty = serialize_records(&headers, &[], OutputFormat::Cbor)?;
+        assert_eq!(
+            decode_binary(empty.as_slice(), InputFormat::Cbor)?,
+            json!([])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_query::unique_names;
+use crate::process::csv_writer::{string_record, RecordWriter};
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::Reader;
+use indexmap::IndexSet;
+use std::io::{BufWriter, Read, Write};
+
+pub fn process_csv_cat(
+    inputs: &[String],
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let readers = inputs
+        .iter()
+        .map(|input| config.open(input))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let output = BufWriter::new(get_writer(output)?);
+    cat_records(readers, config, output, format)?;
+    Ok(())
+}
+
+/// Concatenate the rows of all inputs, columns are matched by name.
+///
+/// The output has every column in order of first appearance, cells of columns an input
+/// doesn't have are left empty. Returns the number of rows written.
+pub fn cat_records<R: Read, W: Write + Send>(
+    mut readers: Vec<Reader<R>>,
+    config: CsvReaderConfig,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<usize> {
+    let mut columns = IndexSet::new();
+    let mut positions = vec![];
+    for reader in &mut readers {
+        let header = config.headers(reader)?;
+        let names = unique_names(header.iter());
+        positions.push(
+            names
+                .into_iter()
+                .map(|name| columns.insert_full(name).0)
+                .collect::<Vec<_>>(),
+        );
+    }
+    let headers = columns.into_iter().collect::<Vec<_>>();
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    for (reader, positions) in readers.iter_mut().zip(&positions) {
+        for record in reader.records() {
+            let record = record?;
+            let mut cells = vec![None; headers.len()];
+            for (cell, &position) in record.iter().zip(positions) {
+                cells[position] = Some(cell);
+            }
+            writer.write(&string_record(&headers, cells))?;
+        }
+    }
+    let count = writer.count();
+    writer.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cat_records() -> anyhow::Result<()> {
+        let config = CsvReaderConfig::default();
+        let first = "Name,Kit Number\nBuffon,77\n";
+        let second = "Nationality,Name\nPoland,Szczesny\n";
+        let mut out = Vec::new();
+        let count = cat_records(
+            vec![
+                config.reader(first.as_bytes()),
+                config.reader(second.as_bytes()),
+            ],
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(out)?,
+            "Name,Kit Number,Nationality\nBuffon,77,\nSzczesny,,Poland\n"
+        );
+        Ok(())
+    }
+}
@@ -1,36 +1,394 @@
+use crate::process::csv_arrow::ParquetOptions;
+use crate::process::csv_encoding::{decode_input, InputEncoding};
+use crate::process::csv_filter::RecordFilter;
+use crate::process::csv_sqlite::export_sqlite;
+use crate::process::csv_types::{merge_record_types, ColumnType};
+use crate::process::csv_writer::RecordWriter;
+use crate::utils::{get_data, get_writer};
 use crate::OutputFormat;
-use csv::Reader;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "PascalCase")]
-struct Player {
-    name: String,
-    position: String,
-    #[serde(rename = "DOB")]
-    dob: String,
-    nationality: String,
-    #[serde(rename = "Kit Number")]
-    kit: u8,
-}
-
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow::Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let header = reader.headers()?.clone();
-    let mut result = vec![];
-    for record in reader.records() {
-        let player = record?;
-        let json_value = header.iter().zip(player.iter()).collect::<Value>();
-        result.push(json_value);
-    }
-
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&result)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&result)?,
-        // OutputFormat::Toml => toml::to_string(&result)?,
+use csv::{Reader, ReaderBuilder, StringRecord};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::{BufWriter, Read, Write};
+
+/// How a csv input should be parsed
+#[derive(Debug, Clone, Copy)]
+pub struct CsvReaderConfig {
+    pub delimiter: u8,
+    pub header: bool,
+    pub encoding: InputEncoding,
+}
+
+impl Default for CsvReaderConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            encoding: InputEncoding::default(),
+        }
+    }
+}
+
+impl CsvReaderConfig {
+    /// Open the input file (`-` for stdin) with this configuration, decoded to utf-8
+    pub fn open(&self, input: &str) -> anyhow::Result<Reader<Box<dyn Read>>> {
+        Ok(self.reader(decode_input(get_data(input)?, self.encoding)?))
+    }
+
+    pub fn reader<R: Read>(&self, reader: R) -> Reader<R> {
+        ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.header)
+            .from_reader(reader)
+    }
+
+    /// Column names of the input, `col1`, `col2`, ... when it has no header row
+    pub fn headers<R: Read>(&self, reader: &mut Reader<R>) -> anyhow::Result<StringRecord> {
+        // without a header row, `headers` peeks at the first record without consuming it
+        let headers = reader.headers()?;
+        if self.header {
+            Ok(headers.clone())
+        } else {
+            Ok((1..=headers.len()).map(|i| format!("col{}", i)).collect())
+        }
+    }
+
+    /// Row number of the first record, row 1 is the header when there is one
+    pub fn first_row(&self) -> usize {
+        1 + self.header as usize
+    }
+}
+
+/// How csv cells are turned into output values
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Detect integers, floats, booleans, nulls and dates per column
+    pub infer_types: bool,
+    /// Forced column types, these win over inferred ones
+    pub column_types: HashMap<String, ColumnType>,
+    /// Columns to keep, by name or 1-based index
+    pub select: Vec<String>,
+    /// Predicates like `Kit Number > 10` a row has to pass
+    pub filters: Vec<String>,
+    /// Sort keys, `column` or `column:desc`
+    pub sort_by: Vec<String>,
+    /// Add rows to an existing sqlite table instead of replacing it
+    pub append: bool,
+    /// Compression and row group size of parquet output
+    pub parquet: ParquetOptions,
+}
+
+impl ConvertOptions {
+    /// Apply forced types on top of the inferred ones, columns are plain strings otherwise
+    pub fn column_types(
+        &self,
+        header: &StringRecord,
+        inferred: Option<Vec<ColumnType>>,
+    ) -> Vec<ColumnType> {
+        let mut types = inferred.unwrap_or_else(|| vec![ColumnType::String; header.len()]);
+        for (ty, name) in types.iter_mut().zip(header.iter()) {
+            if let Some(forced) = self.column_types.get(name) {
+                *ty = *forced;
+            }
+        }
+        types
+    }
+}
+
+/// Convert a csv file record by record, memory use doesn't grow with the input.
+/// Type inference reads an input file twice, stdin is buffered instead as it can
+/// only be read once. Sorting always buffers the matching records.
+///
+/// Parquet and arrow output are always typed, columns are inferred unless declared.
+///
+/// Returns the number of records written.
+pub fn process_csv(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+    options: &ConvertOptions,
+) -> anyhow::Result<usize> {
+    if let OutputFormat::Sqlite = format {
+        return export_sqlite(input, output, config, options);
+    }
+    let infer_types =
+        options.infer_types || matches!(format, OutputFormat::Parquet | OutputFormat::Arrow);
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let filter = RecordFilter::new(&header, &options.select, &options.filters, &options.sort_by)?;
+    let out_header = filter.project(&header);
+    let headers = out_header.iter().map(String::from).collect::<Vec<_>>();
+    let output = BufWriter::new(get_writer(output)?);
+
+    let writer = if filter.needs_sort() || (infer_types && input == "-") {
+        let mut records = vec![];
+        let mut inferred = vec![ColumnType::Null; out_header.len()];
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            if filter.matches(&record) {
+                merge_record_types(&mut inferred, &filter.project(&record));
+                records.push((config.first_row() + i, record));
+            }
+        }
+        // sort keys refer to the input columns, so sort before projecting
+        filter.sort(&mut records, |(_, record)| record);
+        let types = options.column_types(&out_header, infer_types.then_some(inferred));
+        let mut writer = RecordWriter::typed(output, format, headers, &types, options.parquet)?;
+        for (row, record) in &records {
+            let record = filter.project(record);
+            writer.write(&typed_record(&out_header, &types, &record, *row)?)?;
+        }
+        writer
+    } else {
+        let inferred = if infer_types {
+            let mut first_pass = config.open(input)?;
+            config.headers(&mut first_pass)?;
+            Some(infer_reader_types(
+                &mut first_pass,
+                &filter,
+                out_header.len(),
+            )?)
+        } else {
+            None
+        };
+        let types = options.column_types(&out_header, inferred);
+        let mut writer = RecordWriter::typed(output, format, headers, &types, options.parquet)?;
+        convert_records(
+            &mut reader,
+            &filter,
+            &out_header,
+            &types,
+            config.first_row(),
+            &mut writer,
+        )?;
+        writer
     };
 
-    std::fs::write(output, content)?;
+    let count = writer.count();
+    writer.finish()?;
+    Ok(count)
+}
+
+/// Infer column types of the matching records by reading the rest of the input
+pub(crate) fn infer_reader_types<R: Read>(
+    reader: &mut Reader<R>,
+    filter: &RecordFilter,
+    width: usize,
+) -> anyhow::Result<Vec<ColumnType>> {
+    let mut types = vec![ColumnType::Null; width];
+    let mut record = StringRecord::new();
+    while reader.read_record(&mut record)? {
+        if filter.matches(&record) {
+            merge_record_types(&mut types, &filter.project(&record));
+        }
+    }
+    Ok(types)
+}
+
+/// Stream the remaining matching records of a reader into a record writer
+pub fn convert_records<R: Read, W: Write + Send>(
+    reader: &mut Reader<R>,
+    filter: &RecordFilter,
+    header: &StringRecord,
+    types: &[ColumnType],
+    first_row: usize,
+    writer: &mut RecordWriter<W>,
+) -> anyhow::Result<()> {
+    let mut record = StringRecord::new();
+    let mut row = first_row;
+    while reader.read_record(&mut record)? {
+        if filter.matches(&record) {
+            writer.write(&typed_record(header, types, &filter.project(&record), row)?)?;
+        }
+        row += 1;
+    }
     Ok(())
 }
+
+/// Build a json object from a record, converting every cell to its column type
+pub fn typed_record(
+    header: &StringRecord,
+    types: &[ColumnType],
+    record: &StringRecord,
+    row: usize,
+) -> anyhow::Result<Value> {
+    let mut map = Map::new();
+    for ((name, ty), cell) in header.iter().zip(types).zip(record.iter()) {
+        let value = ty
+            .convert(cell)
+            .map_err(|e| anyhow::anyhow!("row {}, column {:?}: {}", row, name, e))?;
+        map.insert(name.to_string(), value);
+    }
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::csv_types::infer_column_types;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_reader_config_delimiter() -> anyhow::Result<()> {
+        let data = "Name;Kit Number\nBuffon;77\n";
+        let config = CsvReaderConfig {
+            delimiter: b';',
+            ..Default::default()
+        };
+        let mut reader = config.reader(data.as_bytes());
+        let headers = config.headers(&mut reader)?;
+        assert_eq!(headers, vec!["Name", "Kit Number"]);
+        let record = reader.records().next().unwrap()?;
+        assert_eq!(record, vec!["Buffon", "77"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_config_without_header() -> anyhow::Result<()> {
+        let data = "Buffon\t77\nPerin\t37\n";
+        let config = CsvReaderConfig {
+            delimiter: b'\t',
+            header: false,
+            ..Default::default()
+        };
+        let mut reader = config.reader(data.as_bytes());
+        let headers = config.headers(&mut reader)?;
+        assert_eq!(headers, vec!["col1", "col2"]);
+        assert_eq!(reader.records().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_record() -> anyhow::Result<()> {
+        let data = "Name,Kit Number,Captain\nBuffon,77,true\nPerin,,false\n";
+        let config = CsvReaderConfig::default();
+        let mut reader = config.reader(data.as_bytes());
+        let header = config.headers(&mut reader)?;
+        let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+
+        let mut options = ConvertOptions {
+            infer_types: true,
+            ..Default::default()
+        };
+        let inferred = infer_column_types(header.len(), &records);
+        let types = options.column_types(&header, Some(inferred.clone()));
+        assert_eq!(
+            typed_record(&header, &types, &records[1], 3)?,
+            serde_json::json!({"Name": "Perin", "Kit Number": null, "Captain": false})
+        );
+
+        options
+            .column_types
+            .insert("Kit Number".to_string(), ColumnType::String);
+        let types = options.column_types(&header, Some(inferred));
+        assert_eq!(
+            typed_record(&header, &types, &records[0], 2)?["Kit Number"],
+            "77"
+        );
+        Ok(())
+    }
+
+    /// Produces a csv file row by row and records how much output had been
+    /// written by the time half of the input was consumed
+    struct GeneratedCsv {
+        rows: usize,
+        next: usize,
+        buf: Vec<u8>,
+        pos: usize,
+        written: Arc<AtomicUsize>,
+        written_at_half: Arc<AtomicUsize>,
+    }
+
+    impl Read for GeneratedCsv {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            if self.pos == self.buf.len() {
+                self.buf.clear();
+                self.pos = 0;
+                if self.next == 0 {
+                    self.buf.extend_from_slice(b"id,name,score\n");
+                } else if self.next <= self.rows {
+                    if self.next == self.rows / 2 {
+                        self.written_at_half
+                            .store(self.written.load(Ordering::Relaxed), Ordering::Relaxed);
+                    }
+                    writeln!(
+                        self.buf,
+                        "{},player {},{}.5",
+                        self.next,
+                        self.next,
+                        self.next % 100
+                    )?;
+                } else {
+                    return Ok(0);
+                }
+                self.next += 1;
+            }
+            let n = out.len().min(self.buf.len() - self.pos);
+            out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    struct CountingSink(Arc<AtomicUsize>);
+
+    impl Write for CountingSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.fetch_add(buf.len(), Ordering::Relaxed);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_convert_records_streams_large_input() -> anyhow::Result<()> {
+        for format in [
+            OutputFormat::Json,
+            OutputFormat::Yaml,
+            OutputFormat::Ndjson,
+            OutputFormat::Cbor,
+            OutputFormat::Msgpack,
+        ] {
+            let rows = 200_000;
+            let written = Arc::new(AtomicUsize::new(0));
+            let written_at_half = Arc::new(AtomicUsize::new(0));
+            let input = GeneratedCsv {
+                rows,
+                next: 0,
+                buf: vec![],
+                pos: 0,
+                written: written.clone(),
+                written_at_half: written_at_half.clone(),
+            };
+
+            let config = CsvReaderConfig::default();
+            let mut reader = config.reader(input);
+            let header = config.headers(&mut reader)?;
+            let types = vec![ColumnType::Integer, ColumnType::String, ColumnType::Float];
+            let headers = header.iter().map(String::from).collect();
+            let mut writer = RecordWriter::new(CountingSink(written.clone()), format, headers)?;
+            let filter = RecordFilter::default();
+            convert_records(
+                &mut reader,
+                &filter,
+                &header,
+                &types,
+                config.first_row(),
+                &mut writer,
+            )?;
+            assert_eq!(writer.count(), rows);
+            writer.finish()?;
+
+            // records were already written out while the rest of the input was still unread
+            let half = written_at_half.load(Ordering::Relaxed);
+            assert!(half > 0, "{} output was held back", format);
+            assert!(half < written.load(Ordering::Relaxed));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::{record_key, resolve_columns};
+use crate::process::csv_query::unique_names;
+use crate::process::csv_writer::{string_record, RecordWriter};
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::Reader;
+use std::collections::HashSet;
+use std::io::{BufWriter, Read, Write};
+
+pub fn process_csv_dedup(
+    input: &str,
+    key: &[String],
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let output = BufWriter::new(get_writer(output)?);
+    dedup_records(config.open(input)?, key, config, output, format)?;
+    Ok(())
+}
+
+/// Keep the first row of every key, the whole row is the key when no columns are given.
+///
+/// Returns the number of rows dropped.
+pub fn dedup_records<R: Read, W: Write + Send>(
+    mut reader: Reader<R>,
+    key: &[String],
+    config: CsvReaderConfig,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<usize> {
+    let header = config.headers(&mut reader)?;
+    let columns = if key.is_empty() {
+        (0..header.len()).collect()
+    } else {
+        resolve_columns(&header, key)?
+    };
+    let headers = unique_names(header.iter());
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    let mut seen = HashSet::new();
+    let mut dropped = 0;
+    for record in reader.records() {
+        let record = record?;
+        if seen.insert(record_key(&record, &columns)) {
+            writer.write(&string_record(&headers, record.iter().map(Some)))?;
+        } else {
+            dropped += 1;
+        }
+    }
+    writer.finish()?;
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_records() -> anyhow::Result<()> {
+        let data = "Name,Kit Number\nBuffon,1\nPerin,37\nBuffon,77\nPerin,37\n";
+        let config = CsvReaderConfig::default();
+
+        let mut out = Vec::new();
+        let dropped = dedup_records(
+            config.reader(data.as_bytes()),
+            &[],
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        assert_eq!(dropped, 1);
+        assert_eq!(out, b"Name,Kit Number\nBuffon,1\nPerin,37\nBuffon,77\n");
+
+        let mut out = Vec::new();
+        let dropped = dedup_records(
+            config.reader(data.as_bytes()),
+            &["Name".to_string()],
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        assert_eq!(dropped, 2);
+        assert_eq!(out, b"Name,Kit Number\nBuffon,1\nPerin,37\n");
+        Ok(())
+    }
+}
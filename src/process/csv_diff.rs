@@ -0,0 +1,299 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::{record_key, resolve_columns};
+use csv::StringRecord;
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::io::Write;
+
+/// A row that differs between two versions of a csv file, identified by the values of
+/// its key columns
+#[derive(Debug, PartialEq)]
+pub enum RowChange {
+    Added {
+        key: Vec<String>,
+        row: Map<String, Value>,
+    },
+    Removed {
+        key: Vec<String>,
+        row: Map<String, Value>,
+    },
+    Changed {
+        key: Vec<String>,
+        cells: Vec<CellChange>,
+    },
+}
+
+impl RowChange {
+    /// Values of the key columns
+    pub fn key_values(&self) -> &[String] {
+        match self {
+            RowChange::Added { key, .. }
+            | RowChange::Removed { key, .. }
+            | RowChange::Changed { key, .. } => key,
+        }
+    }
+
+    /// The key as shown in reports, composite keys joined by `|`
+    pub fn key(&self) -> String {
+        self.key_values().join("|")
+    }
+}
+
+/// A cell that differs, `None` when the column only exists on one side
+#[derive(Debug, PartialEq)]
+pub struct CellChange {
+    pub column: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for RowChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let row = |row: &Map<String, Value>| {
+            row.iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let cell = |cell: &Option<String>| match cell {
+            Some(cell) => format!("{:?}", cell),
+            None => "(missing)".to_string(),
+        };
+        let key = self.key();
+        match self {
+            RowChange::Added { row: r, .. } => write!(f, "+ {}: {}", key, row(r)),
+            RowChange::Removed { row: r, .. } => write!(f, "- {}: {}", key, row(r)),
+            RowChange::Changed { cells, .. } => {
+                let cells = cells
+                    .iter()
+                    .map(|c| format!("{}: {} -> {}", c.column, cell(&c.old), cell(&c.new)))
+                    .collect::<Vec<_>>();
+                write!(f, "~ {}: {}", key, cells.join(", "))
+            }
+        }
+    }
+}
+
+pub fn process_csv_diff(
+    old: &str,
+    new: &str,
+    key: &[String],
+    json: bool,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let (old_header, old_records) = read_all(old, config)?;
+    let (new_header, new_records) = read_all(new, config)?;
+    let changes = diff_records(&old_header, &old_records, &new_header, &new_records, key)?;
+
+    let mut stdout = std::io::stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &json_patch(&changes))?;
+        writeln!(stdout)?;
+    } else {
+        for change in &changes {
+            writeln!(stdout, "{}", change)?;
+        }
+        let count = |f: fn(&RowChange) -> bool| changes.iter().filter(|c| f(c)).count();
+        writeln!(
+            stdout,
+            "{} added, {} removed, {} changed",
+            count(|c| matches!(c, RowChange::Added { .. })),
+            count(|c| matches!(c, RowChange::Removed { .. })),
+            count(|c| matches!(c, RowChange::Changed { .. })),
+        )?;
+    }
+    Ok(())
+}
+
+fn read_all(
+    input: &str,
+    config: CsvReaderConfig,
+) -> anyhow::Result<(StringRecord, Vec<StringRecord>)> {
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    Ok((header, records))
+}
+
+/// Compare two versions of a file by the key columns, which have to be unique in both.
+///
+/// Removed and changed rows come in the order of the old file, added rows in the order
+/// of the new file.
+pub fn diff_records(
+    old_header: &StringRecord,
+    old_records: &[StringRecord],
+    new_header: &StringRecord,
+    new_records: &[StringRecord],
+    key: &[String],
+) -> anyhow::Result<Vec<RowChange>> {
+    let old = index_by_key(old_header, old_records, key)?;
+    let mut new = index_by_key(new_header, new_records, key)?;
+    // columns of both files, old ones first
+    let mut columns = old_header.iter().collect::<Vec<_>>();
+    columns.extend(
+        new_header
+            .iter()
+            .filter(|h| !old_header.iter().any(|o| o == *h)),
+    );
+    let cell = |header: &StringRecord, record: &StringRecord, column: &str| {
+        let i = header.iter().position(|h| h == column)?;
+        Some(record.get(i).unwrap_or_default().to_string())
+    };
+
+    let mut changes = vec![];
+    for (key, old_record) in old {
+        match new.shift_remove(&key) {
+            None => changes.push(RowChange::Removed {
+                key,
+                row: row_map(old_header, old_record),
+            }),
+            Some(new_record) => {
+                let cells = columns
+                    .iter()
+                    .filter_map(|&column| {
+                        let old = cell(old_header, old_record, column);
+                        let new = cell(new_header, new_record, column);
+                        (old != new).then(|| CellChange {
+                            column: column.to_string(),
+                            old,
+                            new,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                if !cells.is_empty() {
+                    changes.push(RowChange::Changed { key, cells });
+                }
+            }
+        }
+    }
+    changes.extend(new.into_iter().map(|(key, record)| RowChange::Added {
+        key,
+        row: row_map(new_header, record),
+    }));
+    Ok(changes)
+}
+
+fn index_by_key<'a>(
+    header: &StringRecord,
+    records: &'a [StringRecord],
+    key: &[String],
+) -> anyhow::Result<IndexMap<Vec<String>, &'a StringRecord>> {
+    let columns = resolve_columns(header, key)?;
+    let mut index = IndexMap::new();
+    for record in records {
+        let key = record_key(record, &columns);
+        if index.contains_key(&key) {
+            anyhow::bail!("Duplicate key: {}", key.join("|"));
+        }
+        index.insert(key, record);
+    }
+    Ok(index)
+}
+
+fn row_map(header: &StringRecord, record: &StringRecord) -> Map<String, Value> {
+    header
+        .iter()
+        .zip(record.iter())
+        .map(|(h, cell)| (h.to_string(), Value::from(cell)))
+        .collect()
+}
+
+/// RFC 6902 operations turning the old file into the new one, where a file is seen
+/// as an object of rows keyed by their key, nested one level per column of a composite key
+pub fn json_patch(changes: &[RowChange]) -> Value {
+    let path = |change: &RowChange, column: Option<&str>| {
+        change
+            .key_values()
+            .iter()
+            .map(String::as_str)
+            .chain(column)
+            .map(|p| format!("/{}", p.replace('~', "~0").replace('/', "~1")))
+            .collect::<String>()
+    };
+    let ops = changes
+        .iter()
+        .flat_map(|change| match change {
+            RowChange::Added { row, .. } => {
+                vec![json!({"op": "add", "path": path(change, None), "value": row})]
+            }
+            RowChange::Removed { .. } => {
+                vec![json!({"op": "remove", "path": path(change, None)})]
+            }
+            RowChange::Changed { cells, .. } => cells
+                .iter()
+                .map(|c| {
+                    let path = path(change, Some(&c.column));
+                    match (&c.old, &c.new) {
+                        (None, Some(new)) => json!({"op": "add", "path": path, "value": new}),
+                        (Some(_), None) => json!({"op": "remove", "path": path}),
+                        (_, new) => json!({"op": "replace", "path": path, "value": new}),
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    Value::Array(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(data: &str) -> anyhow::Result<(StringRecord, Vec<StringRecord>)> {
+        let config = CsvReaderConfig::default();
+        let mut reader = config.reader(data.as_bytes());
+        let header = config.headers(&mut reader)?;
+        Ok((header, reader.records().collect::<Result<_, _>>()?))
+    }
+
+    #[test]
+    fn test_diff_records() -> anyhow::Result<()> {
+        let (old_header, old) = records("Name,Kit Number\nBuffon,1\nPerin,37\nBentancur,30\n")?;
+        let (new_header, new) = records("Name,Kit Number\nBuffon,77\nBentancur,30\nRonaldo,7\n")?;
+        let key = ["Name".to_string()];
+        let changes = diff_records(&old_header, &old, &new_header, &new, &key)?;
+        let report = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            report,
+            vec![
+                "~ Buffon: Kit Number: \"1\" -> \"77\"",
+                "- Perin: Name=\"Perin\", Kit Number=\"37\"",
+                "+ Ronaldo: Name=\"Ronaldo\", Kit Number=\"7\"",
+            ]
+        );
+        assert_eq!(
+            json_patch(&changes),
+            json!([
+                {"op": "replace", "path": "/Buffon/Kit Number", "value": "77"},
+                {"op": "remove", "path": "/Perin"},
+                {"op": "add", "path": "/Ronaldo", "value": {"Name": "Ronaldo", "Kit Number": "7"}},
+            ])
+        );
+
+        let (header, dup) = records("Name,Kit Number\nBuffon,1\nBuffon,77\n")?;
+        assert!(diff_records(&header, &dup, &new_header, &new, &key).is_err());
+
+        // composite keys whose joined values look alike are still different rows
+        let (header, old) = records("a,b,n\na|b,c,1\na,b|c,2\n")?;
+        let (_, new) = records("a,b,n\na|b,c,1\na,b|c,3\n")?;
+        let key = ["a".to_string(), "b".to_string()];
+        let changes = diff_records(&header, &old, &header, &new, &key)?;
+        assert_eq!(
+            changes,
+            vec![RowChange::Changed {
+                key: vec!["a".to_string(), "b|c".to_string()],
+                cells: vec![CellChange {
+                    column: "n".to_string(),
+                    old: Some("2".to_string()),
+                    new: Some("3".to_string()),
+                }],
+            }]
+        );
+        assert_eq!(
+            json_patch(&changes),
+            json!([{"op": "replace", "path": "/a/b|c/n", "value": "3"}])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::str::FromStr;
+
+/// Bytes looked at when guessing the encoding of an input without a BOM
+const SNIFF_SIZE: usize = 64 * 1024;
+
+/// Character encoding of csv input, which is decoded to utf-8 before parsing.
+///
+/// A BOM always wins over the configured encoding and is stripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// guess from the start of the input when there is no BOM
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl Default for InputEncoding {
+    fn default() -> Self {
+        InputEncoding::Fixed(UTF_8)
+    }
+}
+
+impl FromStr for InputEncoding {
+    type Err = anyhow::Error;
+
+    /// `auto` or any WHATWG label, e.g. `windows-1252`, `gbk`, `utf-16le`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(InputEncoding::Auto);
+        }
+        Encoding::for_label(s.trim().as_bytes())
+            .map(InputEncoding::Fixed)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported encoding: {}", s))
+    }
+}
+
+impl fmt::Display for InputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputEncoding::Auto => write!(f, "auto"),
+            InputEncoding::Fixed(encoding) => write!(f, "{}", encoding.name()),
+        }
+    }
+}
+
+/// Wrap a reader so it yields utf-8. Utf-8 input is passed through as is,
+/// invalid sequences are left for the csv reader to report.
+pub(crate) fn decode_input(
+    mut reader: Box<dyn Read>,
+    encoding: InputEncoding,
+) -> anyhow::Result<Box<dyn Read>> {
+    let encoding = match encoding {
+        InputEncoding::Fixed(encoding) => encoding,
+        InputEncoding::Auto => {
+            let mut prefix = Vec::with_capacity(SNIFF_SIZE);
+            (&mut reader)
+                .take(SNIFF_SIZE as u64)
+                .read_to_end(&mut prefix)?;
+            let encoding = detect_encoding(&prefix, prefix.len() < SNIFF_SIZE);
+            reader = Box::new(Cursor::new(prefix).chain(reader));
+            encoding
+        }
+    };
+    let mut builder = DecodeReaderBytesBuilder::new();
+    builder
+        .utf8_passthru(true)
+        .strip_bom(true)
+        .bom_override(true);
+    if encoding != UTF_8 {
+        builder.encoding(Some(encoding));
+    }
+    Ok(Box::new(builder.build(reader)))
+}
+
+/// The BOM's encoding if there is one, otherwise a guess from the bytes
+fn detect_encoding(prefix: &[u8], complete: bool) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(prefix) {
+        return encoding;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(prefix, complete);
+    detector.guess(None, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8], encoding: &str) -> anyhow::Result<String> {
+        let mut reader = decode_input(Box::new(Cursor::new(data.to_vec())), encoding.parse()?)?;
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_decode_input() -> anyhow::Result<()> {
+        assert_eq!(
+            decode(b"\xEF\xBB\xBFName\nBuffon\n", "utf-8")?,
+            "Name\nBuffon\n"
+        );
+        assert_eq!(
+            decode(b"Name\nSzcz\xEAsny\n", "windows-1252")?,
+            "Name\nSzczêsny\n"
+        );
+        let utf16 = b"\xFF\xFEN\0a\0m\0e\0\n\0";
+        assert_eq!(decode(utf16, "utf-8")?, "Name\n");
+        assert_eq!(decode(utf16, "auto")?, "Name\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_encoding() -> anyhow::Result<()> {
+        let (gbk, _, _) = encoding_rs::GBK.encode("姓名,国家\n布冯,意大利\n基耶利尼,意大利\n");
+        assert_eq!(
+            decode(&gbk, "auto")?,
+            "姓名,国家\n布冯,意大利\n基耶利尼,意大利\n"
+        );
+        assert_eq!(decode("Müller\n".as_bytes(), "auto")?, "Müller\n");
+        assert!("klingon".parse::<InputEncoding>().is_err());
+        Ok(())
+    }
+}
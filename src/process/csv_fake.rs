@@ -0,0 +1,401 @@
+use crate::process::csv_arrow::ParquetOptions;
+use crate::process::csv_types::ColumnType;
+use crate::process::csv_validate::ordered_columns;
+use crate::process::csv_writer::RecordWriter;
+use crate::utils::{get_data, get_writer};
+use crate::OutputFormat;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Duration, NaiveDate};
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::{BufWriter, Read, Write};
+
+const FIRST_NAMES: &[&str] = &[
+    "Adrien",
+    "Aaron",
+    "Alex",
+    "Alvaro",
+    "Angel",
+    "Blaise",
+    "Carlo",
+    "Cristiano",
+    "Daniele",
+    "Douglas",
+    "Emre",
+    "Federico",
+    "Gianluigi",
+    "Giorgio",
+    "Gonzalo",
+    "Juan",
+    "Leonardo",
+    "Luca",
+    "Marco",
+    "Mario",
+    "Mattia",
+    "Merih",
+    "Miralem",
+    "Moise",
+    "Paulo",
+    "Rodrigo",
+    "Sami",
+    "Sandro",
+    "Wojciech",
+    "Alessandro",
+    "Andrea",
+    "Dejan",
+    "Federica",
+    "Sara",
+    "Martina",
+    "Elena",
+    "Chiara",
+    "Giulia",
+    "Anna",
+    "Lucia",
+    "Sofia",
+    "Valentina",
+    "Aurora",
+    "Cecilia",
+    "Barbara",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Bentancur",
+    "Bernardeschi",
+    "Bonucci",
+    "Buffon",
+    "Can",
+    "Chiellini",
+    "Costa",
+    "Cuadrado",
+    "Danilo",
+    "Demiral",
+    "De Ligt",
+    "De Sciglio",
+    "Dybala",
+    "Higuain",
+    "Khedira",
+    "Kean",
+    "Mandzukic",
+    "Matuidi",
+    "Perin",
+    "Pinsoglio",
+    "Pjanic",
+    "Rabiot",
+    "Ramsey",
+    "Ronaldo",
+    "Rugani",
+    "Sandro",
+    "Szczesny",
+    "Rossi",
+    "Russo",
+    "Ferrari",
+    "Esposito",
+    "Bianchi",
+    "Romano",
+    "Colombo",
+    "Ricci",
+    "Marino",
+    "Greco",
+    "Bruno",
+    "Gallo",
+    "Conti",
+    "Giordano",
+    "Mancini",
+    "Rizzo",
+    "Lombardi",
+    "Moretti",
+];
+
+/// Columns of generated rows, loaded from yaml
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FakeSpec {
+    #[serde(deserialize_with = "ordered_columns")]
+    pub columns: IndexMap<String, FakeColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FakeColumn {
+    #[serde(flatten)]
+    pub generator: Generator,
+    /// Share of the cells left empty, between 0 and 1
+    #[serde(default)]
+    pub null_rate: f64,
+}
+
+/// What a column is filled with, selected by `kind`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Generator {
+    /// first and last name
+    Name,
+    FirstName,
+    LastName,
+    /// `first.last<nn>@<domain>`, the domain defaults to `example.com`
+    Email {
+        domain: Option<String>,
+    },
+    /// random (version 4) uuid
+    Uuid,
+    /// uniformly distributed in `min..=max`
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+        /// round to this many decimals
+        decimals: Option<i32>,
+    },
+    Boolean,
+    /// a day in `from..=to`, written as `%Y-%m-%d` unless a chrono `format` is given
+    Date {
+        from: NaiveDate,
+        to: NaiveDate,
+        format: Option<String>,
+    },
+    /// one of the values
+    Enum {
+        values: Vec<String>,
+    },
+}
+
+impl FakeSpec {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let reader = get_data(path)?;
+        Self::from_reader(reader)
+    }
+
+    pub fn from_reader(reader: impl Read) -> anyhow::Result<Self> {
+        let spec: FakeSpec = serde_yaml::from_reader(reader)?;
+        for (name, column) in &spec.columns {
+            column
+                .check()
+                .map_err(|e| anyhow::anyhow!("Invalid spec for column {:?}: {}", name, e))?;
+        }
+        Ok(spec)
+    }
+
+    /// Type of every column, so typed formats like parquet get a proper schema
+    pub fn column_types(&self) -> Vec<ColumnType> {
+        self.columns
+            .values()
+            .map(|column| match &column.generator {
+                Generator::Integer { .. } => ColumnType::Integer,
+                Generator::Float { .. } => ColumnType::Float,
+                Generator::Boolean => ColumnType::Boolean,
+                Generator::Date { format: None, .. } => ColumnType::Date,
+                _ => ColumnType::String,
+            })
+            .collect()
+    }
+
+    /// One row keyed by column name
+    pub fn record(&self, rng: &mut impl Rng) -> Value {
+        let map = self
+            .columns
+            .iter()
+            .map(|(name, column)| {
+                let value = if column.null_rate > 0.0 && rng.gen_bool(column.null_rate) {
+                    Value::Null
+                } else {
+                    column.generator.generate(rng)
+                };
+                (name.clone(), value)
+            })
+            .collect::<Map<String, Value>>();
+        Value::Object(map)
+    }
+}
+
+impl FakeColumn {
+    fn check(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.null_rate) {
+            anyhow::bail!("null_rate must be between 0 and 1");
+        }
+        match &self.generator {
+            Generator::Integer { min, max } if min > max => anyhow::bail!("min is above max"),
+            Generator::Float { min, max, .. } if min > max => anyhow::bail!("min is above max"),
+            Generator::Date { from, to, .. } if from > to => anyhow::bail!("from is after to"),
+            // chrono panics when formatting with an invalid format
+            Generator::Date {
+                format: Some(format),
+                ..
+            } if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) => {
+                anyhow::bail!("invalid date format {:?}", format)
+            }
+            Generator::Enum { values } if values.is_empty() => anyhow::bail!("no values"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Generator {
+    fn generate(&self, rng: &mut impl Rng) -> Value {
+        match self {
+            Generator::Name => {
+                format!("{} {}", pick(FIRST_NAMES, rng), pick(LAST_NAMES, rng)).into()
+            }
+            Generator::FirstName => pick(FIRST_NAMES, rng).into(),
+            Generator::LastName => pick(LAST_NAMES, rng).into(),
+            Generator::Email { domain } => {
+                let local = format!("{}.{}", pick(FIRST_NAMES, rng), pick(LAST_NAMES, rng))
+                    .to_lowercase()
+                    .replace(' ', "");
+                let domain = domain.as_deref().unwrap_or("example.com");
+                format!("{}{:02}@{}", local, rng.gen_range(0..100), domain).into()
+            }
+            Generator::Uuid => uuid_v4(rng).into(),
+            Generator::Integer { min, max } => rng.gen_range(*min..=*max).into(),
+            Generator::Float { min, max, decimals } => {
+                let value = if min == max {
+                    *min
+                } else {
+                    rng.gen_range(*min..*max)
+                };
+                match decimals {
+                    Some(decimals) => {
+                        let scale = 10f64.powi(*decimals);
+                        ((value * scale).round() / scale).into()
+                    }
+                    None => value.into(),
+                }
+            }
+            Generator::Boolean => rng.gen_bool(0.5).into(),
+            Generator::Date { from, to, format } => {
+                let days = (*to - *from).num_days();
+                let date = *from + Duration::days(rng.gen_range(0..=days));
+                let format = format.as_deref().unwrap_or("%Y-%m-%d");
+                date.format(format).to_string().into()
+            }
+            Generator::Enum { values } => values
+                .choose(rng)
+                .expect("checked to be non-empty")
+                .clone()
+                .into(),
+        }
+    }
+}
+
+fn pick<'a>(names: &[&'a str], rng: &mut impl Rng) -> &'a str {
+    names.choose(rng).expect("name lists aren't empty")
+}
+
+fn uuid_v4(rng: &mut impl Rng) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Write `rows` generated rows, the same seed always gives the same rows
+pub fn process_csv_fake(
+    spec: &str,
+    rows: usize,
+    seed: Option<u64>,
+    output: &str,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let spec = FakeSpec::load(spec)?;
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let output = BufWriter::new(get_writer(output)?);
+    fake_records(&spec, rows, &mut rng, output, format)?;
+    Ok(())
+}
+
+pub fn fake_records<W: Write + Send>(
+    spec: &FakeSpec,
+    rows: usize,
+    rng: &mut impl Rng,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<W> {
+    let headers = spec.columns.keys().cloned().collect();
+    let types = spec.column_types();
+    let mut writer =
+        RecordWriter::typed(output, format, headers, &types, ParquetOptions::default())?;
+    for _ in 0..rows {
+        writer.write(&spec.record(rng))?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::CsvReaderConfig;
+
+    fn fake(rows: usize, seed: u64) -> anyhow::Result<String> {
+        let spec = FakeSpec::load("assets/juventus.fake.yaml")?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let out = fake_records(&spec, rows, &mut rng, Vec::new(), OutputFormat::Csv)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_fake_records() -> anyhow::Result<()> {
+        let out = fake(200, 42)?;
+        assert_eq!(out, fake(200, 42)?);
+        assert_ne!(out, fake(200, 7)?);
+
+        let config = CsvReaderConfig::default();
+        let mut reader = config.reader(out.as_bytes());
+        let header = config.headers(&mut reader)?;
+        assert_eq!(
+            header,
+            vec!["Name", "Position", "DOB", "Nationality", "Kit Number"]
+        );
+        let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(records.len(), 200);
+        for record in &records {
+            let kit = record[4].parse::<i64>()?;
+            assert!((1..=99).contains(&kit));
+            assert!(NaiveDate::parse_from_str(&record[2], "%b %d, %Y").is_ok());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_generators() -> anyhow::Result<()> {
+        let spec = FakeSpec::from_reader(
+            "columns:\n  id: {kind: uuid}\n  email: {kind: email, domain: juventus.com}\n  score: {kind: float, min: 0, max: 1, decimals: 2}\n  note: {kind: enum, values: [a], null_rate: 1}\n"
+                .as_bytes(),
+        )?;
+        let record = spec.record(&mut StdRng::seed_from_u64(1));
+        let id = record["id"].as_str().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(record["email"].as_str().unwrap().ends_with("@juventus.com"));
+        assert!(record["score"].as_f64().unwrap() <= 1.0);
+        assert!(record["note"].is_null());
+
+        for invalid in [
+            "columns:\n  n: {kind: integer, min: 5, max: 1}\n",
+            "columns:\n  d: {kind: date, from: 2000-01-01, to: 2000-12-31, format: '%Q'}\n",
+            "columns:\n  e: {kind: enum, values: [], null_rate: 0.5}\n",
+        ] {
+            assert!(FakeSpec::from_reader(invalid.as_bytes()).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use csv::StringRecord;
+use std::cmp::Ordering;
+
+/// Column selection, row predicates and sort keys applied to csv records
+#[derive(Debug, Clone, Default)]
+pub struct RecordFilter {
+    select: Option<Vec<usize>>,
+    predicates: Vec<Predicate>,
+    sort_keys: Vec<SortKey>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// A `column op value` comparison such as `Kit Number > 10`
+#[derive(Debug, Clone)]
+struct Predicate {
+    column: usize,
+    op: Op,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SortKey {
+    column: usize,
+    descending: bool,
+}
+
+// two character operators first so `>=` isn't read as `>`
+const OPERATORS: [(&str, Op); 7] = [
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+    ("=", Op::Eq),
+];
+
+impl RecordFilter {
+    /// Resolve `--select`, `--where` and `--sort-by` specs against the header
+    pub fn new(
+        header: &StringRecord,
+        select: &[String],
+        predicates: &[String],
+        sort_by: &[String],
+    ) -> anyhow::Result<Self> {
+        let select = if select.is_empty() {
+            None
+        } else {
+            Some(resolve_columns(header, select)?)
+        };
+        let predicates = predicates
+            .iter()
+            .map(|expr| Predicate::parse(header, expr))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let sort_keys = sort_by
+            .iter()
+            .map(|spec| SortKey::parse(header, spec))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            select,
+            predicates,
+            sort_keys,
+        })
+    }
+
+    /// Whether records have to be collected before they can be written out
+    pub fn needs_sort(&self) -> bool {
+        !self.sort_keys.is_empty()
+    }
+
+    /// Whether a record passes every predicate
+    pub fn matches(&self, record: &StringRecord) -> bool {
+        self.predicates.iter().all(|p| p.matches(record))
+    }
+
+    /// Keep only the selected columns, in the order they were selected
+    pub fn project(&self, record: &StringRecord) -> StringRecord {
+        match &self.select {
+            Some(columns) => columns
+                .iter()
+                .map(|&i| record.get(i).unwrap_or_default())
+                .collect(),
+            None => record.clone(),
+        }
+    }
+
+    /// Stable sort on all sort keys, the first key is the most significant
+    pub fn sort<T>(&self, records: &mut [T], record: impl Fn(&T) -> &StringRecord) {
+        records.sort_by(|a, b| {
+            let (a, b) = (record(a), record(b));
+            self.sort_keys
+                .iter()
+                .map(|key| {
+                    let ordering = compare_cells(
+                        a.get(key.column).unwrap_or_default(),
+                        b.get(key.column).unwrap_or_default(),
+                    );
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+}
+
+impl Predicate {
+    fn parse(header: &StringRecord, expr: &str) -> anyhow::Result<Self> {
+        let (pos, symbol, op) = find_operator(expr)
+            .ok_or_else(|| anyhow::anyhow!("No comparison operator in {:?}", expr))?;
+        let column = resolve_column(header, expr[..pos].trim())?;
+        let value = unquote(expr[pos + symbol.len()..].trim()).to_string();
+        Ok(Self { column, op, value })
+    }
+
+    fn matches(&self, record: &StringRecord) -> bool {
+        let cell = record.get(self.column).unwrap_or_default();
+        let ordering = compare_cells(cell, &self.value);
+        match self.op {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+        }
+    }
+}
+
+impl SortKey {
+    fn parse(header: &StringRecord, spec: &str) -> anyhow::Result<Self> {
+        let (name, descending) = match spec.rsplit_once(':') {
+            Some((name, "desc")) => (name, true),
+            Some((name, "asc")) => (name, false),
+            _ => (spec, false),
+        };
+        let column = resolve_column(header, name.trim())?;
+        Ok(Self { column, descending })
+    }
+}
+
+/// Find a column by name, falling back to a 1-based index
+pub fn resolve_column(header: &StringRecord, name: &str) -> anyhow::Result<usize> {
+    let name = unquote(name);
+    if let Some(i) = header.iter().position(|h| h == name) {
+        return Ok(i);
+    }
+    match name.parse::<usize>() {
+        Ok(i) if (1..=header.len()).contains(&i) => Ok(i - 1),
+        _ => anyhow::bail!("Unknown column: {}", name),
+    }
+}
+
+/// Resolve several columns, e.g. the key columns of a join
+pub fn resolve_columns(header: &StringRecord, names: &[String]) -> anyhow::Result<Vec<usize>> {
+    names
+        .iter()
+        .map(|name| resolve_column(header, name))
+        .collect()
+}
+
+/// Values of the key columns of a record, missing cells are empty
+pub fn record_key(record: &StringRecord, columns: &[usize]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|&i| record.get(i).unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Numbers sort before text, numbers compare numerically and text as text.
+///
+/// Keeping the two apart makes this a total order even for columns mixing both.
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Position, symbol and kind of the first operator outside of quotes
+fn find_operator(expr: &str) -> Option<(usize, &'static str, Op)> {
+    let mut quote = None;
+    for (i, c) in expr.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, _) => {
+                if let Some((symbol, op)) = OPERATORS.iter().find(|(s, _)| expr[i..].starts_with(s))
+                {
+                    return Some((i, symbol, *op));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn unquote(s: &str) -> &str {
+    let quoted = s.len() >= 2
+        && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')));
+    if quoted {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> StringRecord {
+        StringRecord::from(vec!["Name", "Position", "Kit Number"])
+    }
+
+    #[test]
+    fn test_predicates() -> anyhow::Result<()> {
+        let filter = RecordFilter::new(
+            &header(),
+            &[],
+            &[
+                "Position == \"Goalkeeper\"".to_string(),
+                "Kit Number > 10".to_string(),
+            ],
+            &[],
+        )?;
+        assert!(filter.matches(&StringRecord::from(vec!["Buffon", "Goalkeeper", "77"])));
+        assert!(!filter.matches(&StringRecord::from(vec!["Szczesny", "Goalkeeper", "1"])));
+        assert!(!filter.matches(&StringRecord::from(vec!["Chiellini", "Defender", "3"])));
+        // `10` is not less than `9` as a number even though it sorts before it as text
+        let filter = RecordFilter::new(&header(), &[], &["3 < 9".to_string()], &[])?;
+        assert!(!filter.matches(&StringRecord::from(vec!["Khedira", "Midfielder", "10"])));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_cells_mixed() {
+        let mut cells = vec!["1a", "10", "b", "2", "-3.5", "", "2"];
+        cells.sort_by(|a, b| compare_cells(a, b));
+        assert_eq!(cells, vec!["-3.5", "2", "2", "10", "", "1a", "b"]);
+        for a in &cells {
+            for b in &cells {
+                assert_eq!(compare_cells(a, b), compare_cells(b, a).reverse());
+                for c in &cells {
+                    if compare_cells(a, b).is_le() && compare_cells(b, c).is_le() {
+                        assert!(compare_cells(a, c).is_le());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_and_sort() -> anyhow::Result<()> {
+        let filter = RecordFilter::new(
+            &header(),
+            &["Kit Number".to_string(), "1".to_string()],
+            &[],
+            &["Position".to_string(), "Kit Number:desc".to_string()],
+        )?;
+        let mut records = vec![
+            StringRecord::from(vec!["Dybala", "Forward", "10"]),
+            StringRecord::from(vec!["Perin", "Goalkeeper", "37"]),
+            StringRecord::from(vec!["Ronaldo", "Forward", "7"]),
+            StringRecord::from(vec!["Buffon", "Goalkeeper", "77"]),
+        ];
+        filter.sort(&mut records, |r| r);
+        let projected = records
+            .iter()
+            .map(|r| filter.project(r))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            projected,
+            vec![
+                StringRecord::from(vec!["10", "Dybala"]),
+                StringRecord::from(vec!["7", "Ronaldo"]),
+                StringRecord::from(vec!["77", "Buffon"]),
+                StringRecord::from(vec!["37", "Perin"]),
+            ]
+        );
+        assert!(RecordFilter::new(&header(), &["Age".to_string()], &[], &[]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::process::csv_arrow::read_columnar;
+use crate::process::csv_binary::decode_binary;
+use crate::process::csv_writer::{value_to_cell, RecordWriter};
+use crate::utils::{get_data, get_writer};
+use crate::{InputFormat, OutputFormat};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, BufWriter, Read};
+
+/// Convert records into csv, or any other streamed format with `output_format`.
+///
+/// Typed inputs like parquet keep their numbers and booleans in formats that have them,
+/// e.g. json; `delimiter` only applies to csv output.
+pub fn process_csv_from(
+    input: &str,
+    output: &str,
+    format: InputFormat,
+    output_format: OutputFormat,
+    delimiter: u8,
+) -> anyhow::Result<()> {
+    let reader = get_data(input)?;
+    let records = read_records(reader, format)?
+        .iter()
+        .map(flatten_record)
+        .collect::<Vec<_>>();
+    let headers = union_headers(&records);
+
+    if !matches!(output_format, OutputFormat::Csv) {
+        let output = BufWriter::new(get_writer(output)?);
+        let mut writer = RecordWriter::new(output, output_format, headers)?;
+        for record in records {
+            writer.write(&Value::Object(record))?;
+        }
+        writer.finish()?;
+        return Ok(());
+    }
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(get_writer(output)?);
+    writer.write_record(&headers)?;
+    for record in &records {
+        writer.write_record(
+            headers
+                .iter()
+                .map(|h| record.get(h).map(value_to_cell).unwrap_or_default()),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read an array of records, a single top level object counts as one record
+fn read_records(reader: impl Read, format: InputFormat) -> anyhow::Result<Vec<Value>> {
+    let value = match format {
+        InputFormat::Json => serde_json::from_reader(reader)?,
+        InputFormat::Yaml => serde_yaml::from_reader(reader)?,
+        InputFormat::Parquet | InputFormat::Arrow => Value::Array(read_columnar(reader, format)?),
+        InputFormat::Msgpack | InputFormat::Cbor => decode_binary(reader, format)?,
+        InputFormat::Ndjson => {
+            let mut records = vec![];
+            for line in BufReader::new(reader).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    records.push(serde_json::from_str(&line)?);
+                }
+            }
+            Value::Array(records)
+        }
+    };
+    match value {
+        Value::Array(records) => Ok(records),
+        Value::Object(_) => Ok(vec![value]),
+        _ => anyhow::bail!("Expected an array of objects"),
+    }
+}
+
+/// Flatten nested objects into dotted column names, e.g. `address.city`
+pub(crate) fn flatten_record(record: &Value) -> Map<String, Value> {
+    let mut map = Map::new();
+    match record {
+        Value::Object(_) => flatten_into(&mut map, "", record),
+        // a scalar or array row becomes a single column
+        v => {
+            map.insert("value".to_string(), flatten_leaf(v));
+        }
+    }
+    map
+}
+
+fn flatten_into(map: &mut Map<String, Value>, prefix: &str, value: &Value) {
+    match value {
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                flatten_into(map, &key, v);
+            }
+        }
+        v => {
+            map.insert(prefix.to_string(), flatten_leaf(v));
+        }
+    }
+}
+
+fn flatten_leaf(value: &Value) -> Value {
+    match value {
+        // arrays don't map onto columns, keep them as a json string in one cell
+        Value::Array(_) => Value::String(value.to_string()),
+        v => v.clone(),
+    }
+}
+
+/// Union of all keys, in order of first appearance
+fn union_headers(records: &[Map<String, Value>]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut headers = vec![];
+    for record in records {
+        for key in record.keys() {
+            if seen.insert(key.as_str()) {
+                headers.push(key.clone());
+            }
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::csv_arrow::{ColumnarWriter, ParquetOptions};
+    use crate::process::csv_types::ColumnType;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten_record() {
+        let record = json!({
+            "name": "Buffon",
+            "address": {"city": "Turin", "zip": 10100},
+            "tags": ["gk", "captain"],
+        });
+        let flat = flatten_record(&record);
+        assert_eq!(
+            flat.keys().collect::<Vec<_>>(),
+            vec!["name", "address.city", "address.zip", "tags"]
+        );
+        assert_eq!(flat["address.zip"], json!(10100));
+        assert_eq!(flat["tags"], json!(r#"["gk","captain"]"#));
+    }
+
+    #[test]
+    fn test_union_headers() -> anyhow::Result<()> {
+        let data = "{\"a\": 1, \"b\": 2}\n\n{\"c\": {\"d\": 3}, \"a\": 4}\n";
+        let records = read_records(data.as_bytes(), InputFormat::Ndjson)?
+            .iter()
+            .map(flatten_record)
+            .collect::<Vec<_>>();
+        assert_eq!(union_headers(&records), vec!["a", "b", "c.d"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parquet_to_ndjson() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-from-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let parquet = dir.join("data.parquet");
+        let ndjson = dir.join("data.ndjson");
+
+        let headers = vec!["Name".to_string(), "Kit Number".to_string()];
+        let types = [ColumnType::String, ColumnType::Integer];
+        let file = std::fs::File::create(&parquet)?;
+        let mut writer = ColumnarWriter::new(
+            file,
+            OutputFormat::Parquet,
+            headers,
+            &types,
+            ParquetOptions::default(),
+        )?;
+        writer.write(&json!({"Name": "Buffon", "Kit Number": 77}))?;
+        writer.write(&json!({"Name": "Perin", "Kit Number": null}))?;
+        writer.finish()?;
+
+        process_csv_from(
+            &parquet.to_string_lossy(),
+            &ndjson.to_string_lossy(),
+            InputFormat::Parquet,
+            OutputFormat::Ndjson,
+            b',',
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(&ndjson)?,
+            "{\"Name\":\"Buffon\",\"Kit Number\":77}\n{\"Name\":\"Perin\",\"Kit Number\":null}\n"
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::{compare_cells, record_key, resolve_column, resolve_columns};
+use crate::process::csv_stats::number;
+use crate::process::csv_types::ColumnType;
+use crate::process::csv_writer::RecordWriter;
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::StringRecord;
+use indexmap::{IndexMap, IndexSet};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::BufWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggFunc {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Distinct,
+}
+
+impl AggFunc {
+    fn name(self) -> &'static str {
+        match self {
+            AggFunc::Count => "count",
+            AggFunc::Sum => "sum",
+            AggFunc::Avg => "avg",
+            AggFunc::Min => "min",
+            AggFunc::Max => "max",
+            AggFunc::Distinct => "distinct",
+        }
+    }
+}
+
+/// An aggregate spec such as `count` or `avg:Kit Number`
+#[derive(Debug, Clone)]
+struct Aggregate {
+    func: AggFunc,
+    column: Option<usize>,
+    name: String,
+}
+
+/// Running state of one aggregate over the rows of a group
+#[derive(Debug, Default, Clone)]
+struct Accumulator {
+    count: usize,
+    numbers: usize,
+    sum: f64,
+    min: Option<String>,
+    max: Option<String>,
+    distinct: HashSet<String>,
+}
+
+impl Aggregate {
+    fn parse(header: &StringRecord, spec: &str) -> anyhow::Result<Self> {
+        let (func, column) = match spec.split_once(':') {
+            Some((func, column)) => (func.trim(), Some(column.trim())),
+            None => (spec.trim(), None),
+        };
+        let func = match func.to_lowercase().as_str() {
+            "count" => AggFunc::Count,
+            "sum" => AggFunc::Sum,
+            "avg" | "mean" => AggFunc::Avg,
+            "min" => AggFunc::Min,
+            "max" => AggFunc::Max,
+            "distinct" => AggFunc::Distinct,
+            v => anyhow::bail!("Unsupported aggregate: {}", v),
+        };
+        let column = match column {
+            Some(name) => Some(resolve_column(header, name)?),
+            None if func == AggFunc::Count => None,
+            None => anyhow::bail!("{} needs a column, e.g. {}:Kit Number", spec, spec),
+        };
+        let name = match column {
+            Some(i) => format!("{}({})", func.name(), &header[i]),
+            None => "count".to_string(),
+        };
+        Ok(Self { func, column, name })
+    }
+
+    fn add(&self, acc: &mut Accumulator, record: &StringRecord) {
+        let Some(column) = self.column else {
+            acc.count += 1;
+            return;
+        };
+        let cell = record.get(column).unwrap_or_default();
+        if ColumnType::detect(cell) == ColumnType::Null {
+            return;
+        }
+        acc.count += 1;
+        if let Ok(n) = cell.trim().parse::<f64>() {
+            acc.numbers += 1;
+            acc.sum += n;
+        }
+        match self.func {
+            AggFunc::Min
+                if acc
+                    .min
+                    .as_deref()
+                    .is_none_or(|min| compare_cells(cell, min).is_lt()) =>
+            {
+                acc.min = Some(cell.to_string())
+            }
+            AggFunc::Max
+                if acc
+                    .max
+                    .as_deref()
+                    .is_none_or(|max| compare_cells(cell, max).is_gt()) =>
+            {
+                acc.max = Some(cell.to_string())
+            }
+            AggFunc::Distinct => {
+                acc.distinct.insert(cell.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn result(&self, acc: &Accumulator) -> Value {
+        let cell = |cell: &Option<String>| match cell {
+            Some(cell) => match cell.trim().parse::<f64>() {
+                Ok(n) => number(n),
+                Err(_) => Value::from(cell.as_str()),
+            },
+            None => Value::Null,
+        };
+        match self.func {
+            AggFunc::Count => Value::from(acc.count),
+            AggFunc::Sum if acc.numbers > 0 => number(acc.sum),
+            AggFunc::Avg if acc.numbers > 0 => Value::from(acc.sum / acc.numbers as f64),
+            AggFunc::Sum | AggFunc::Avg => Value::Null,
+            AggFunc::Min => cell(&acc.min),
+            AggFunc::Max => cell(&acc.max),
+            AggFunc::Distinct => Value::from(acc.distinct.len()),
+        }
+    }
+}
+
+pub fn process_csv_group(
+    input: &str,
+    by: &[String],
+    aggs: &[String],
+    pivot: Option<&str>,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let (headers, rows) = group_records(&header, reader.records(), by, aggs, pivot)?;
+
+    let output = BufWriter::new(get_writer(output)?);
+    let mut writer = RecordWriter::new(output, format, headers)?;
+    for row in &rows {
+        writer.write(row)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Aggregate the records of every group of `by` columns, groups in order of first appearance.
+///
+/// With a pivot column, each distinct value of it becomes a column holding the single
+/// aggregate of the rows with that value. A value equal to a group column name becomes
+/// `<pivot>:<value>` instead.
+pub fn group_records(
+    header: &StringRecord,
+    records: impl Iterator<Item = csv::Result<StringRecord>>,
+    by: &[String],
+    aggs: &[String],
+    pivot: Option<&str>,
+) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    let by = resolve_columns(header, by)?;
+    let aggs = if aggs.is_empty() {
+        vec![Aggregate::parse(header, "count")?]
+    } else {
+        aggs.iter()
+            .map(|spec| Aggregate::parse(header, spec))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let pivot = pivot.map(|name| resolve_column(header, name)).transpose()?;
+    if pivot.is_some() && aggs.len() != 1 {
+        anyhow::bail!("A pivot table takes exactly one aggregate");
+    }
+
+    // group key -> pivot value (empty without a pivot) -> one accumulator per aggregate
+    let mut groups: IndexMap<Vec<String>, HashMap<String, Vec<Accumulator>>> = IndexMap::new();
+    let mut pivot_values = IndexSet::new();
+    for record in records {
+        let record = record?;
+        let value = pivot
+            .map(|i| record.get(i).unwrap_or_default().to_string())
+            .unwrap_or_default();
+        let accs = groups
+            .entry(record_key(&record, &by))
+            .or_default()
+            .entry(value.clone())
+            .or_insert_with(|| vec![Accumulator::default(); aggs.len()]);
+        for (agg, acc) in aggs.iter().zip(accs.iter_mut()) {
+            agg.add(acc, &record);
+        }
+        pivot_values.insert(value);
+    }
+
+    let mut headers = by
+        .iter()
+        .map(|&i| header[i].to_string())
+        .collect::<Vec<_>>();
+    // pivot value -> its column
+    let mut pivot_columns = IndexMap::new();
+    if let Some(pivot) = pivot {
+        for value in &pivot_values {
+            let mut column = value.clone();
+            if headers.contains(&column) {
+                column = format!("{}:{}", &header[pivot], value);
+                if headers.contains(&column) || pivot_values.contains(&column) {
+                    anyhow::bail!("Pivot value {:?} clashes with a group column", value);
+                }
+            }
+            pivot_columns.insert(value.clone(), column);
+        }
+    }
+    match pivot {
+        Some(_) => headers.extend(pivot_columns.values().cloned()),
+        None => headers.extend(aggs.iter().map(|agg| agg.name.clone())),
+    }
+    let empty = vec![Accumulator::default(); aggs.len()];
+    let rows = groups
+        .into_iter()
+        .map(|(key, cells)| {
+            let mut row = by
+                .iter()
+                .map(|&i| header[i].to_string())
+                .zip(key.into_iter().map(Value::from))
+                .collect::<Map<_, _>>();
+            if pivot.is_some() {
+                for (value, column) in &pivot_columns {
+                    let accs = cells.get(value).unwrap_or(&empty);
+                    row.insert(column.clone(), aggs[0].result(&accs[0]));
+                }
+            } else {
+                let accs = cells.get("").unwrap_or(&empty);
+                for (agg, acc) in aggs.iter().zip(accs) {
+                    row.insert(agg.name.clone(), agg.result(acc));
+                }
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Ok((headers, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_group_juventus() -> anyhow::Result<()> {
+        let config = CsvReaderConfig::default();
+        let mut reader = config.open("assets/juventus.csv")?;
+        let header = config.headers(&mut reader)?;
+        let (headers, rows) = group_records(
+            &header,
+            reader.records(),
+            &["Nationality".to_string()],
+            &["count".to_string(), "max:Kit Number".to_string()],
+            None,
+        )?;
+        assert_eq!(headers, vec!["Nationality", "count", "max(Kit Number)"]);
+        let italy = rows.iter().find(|r| r["Nationality"] == "Italy").unwrap();
+        assert_eq!(italy["count"], 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregates_and_pivot() -> anyhow::Result<()> {
+        let data = "Team,Position,Goals\nA,Forward,10\nA,Forward,4\nA,Defender,1\nB,Forward,7\nB,Defender,\n";
+        let config = CsvReaderConfig::default();
+        let group = |aggs: &[&str], pivot| {
+            let mut reader = config.reader(data.as_bytes());
+            let header = config.headers(&mut reader)?;
+            let aggs = aggs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            group_records(
+                &header,
+                reader.records(),
+                &["Team".to_string()],
+                &aggs,
+                pivot,
+            )
+        };
+
+        let (_, rows) = group(
+            &[
+                "count",
+                "sum:Goals",
+                "avg:Goals",
+                "min:Goals",
+                "distinct:Position",
+            ],
+            None,
+        )?;
+        assert_eq!(
+            rows,
+            vec![
+                json!({"Team": "A", "count": 3, "sum(Goals)": 15, "avg(Goals)": 5.0, "min(Goals)": 1, "distinct(Position)": 2}),
+                json!({"Team": "B", "count": 2, "sum(Goals)": 7, "avg(Goals)": 7.0, "min(Goals)": 7, "distinct(Position)": 2}),
+            ]
+        );
+
+        let (headers, rows) = group(&["sum:Goals"], Some("Position"))?;
+        assert_eq!(headers, vec!["Team", "Forward", "Defender"]);
+        assert_eq!(
+            rows,
+            vec![
+                json!({"Team": "A", "Forward": 14, "Defender": 1}),
+                json!({"Team": "B", "Forward": 7, "Defender": null}),
+            ]
+        );
+        assert!(group(&["count", "sum:Goals"], Some("Position")).is_err());
+        assert!(group(&["sum"], None).is_err());
+
+        // a pivot value named like the group column doesn't overwrite the group key
+        let config = CsvReaderConfig::default();
+        let data = "Team,Role\nA,Team\nA,Forward\nB,Team\n";
+        let mut reader = config.reader(data.as_bytes());
+        let header = config.headers(&mut reader)?;
+        let (headers, rows) = group_records(
+            &header,
+            reader.records(),
+            &["Team".to_string()],
+            &[],
+            Some("Role"),
+        )?;
+        assert_eq!(headers, vec!["Team", "Role:Team", "Forward"]);
+        assert_eq!(rows[1], json!({"Team": "B", "Role:Team": 1, "Forward": 0}));
+        Ok(())
+    }
+}
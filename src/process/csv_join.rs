@@ -0,0 +1,169 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::{record_key, resolve_columns};
+use crate::process::csv_query::unique_names;
+use crate::process::csv_writer::{string_record, RecordWriter};
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::{Reader, StringRecord};
+use std::collections::HashMap;
+use std::io::{BufWriter, Read, Write};
+use std::str::FromStr;
+
+/// Which unmatched rows a join keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only rows with a match on both sides
+    Inner,
+    /// Every left row, right columns are empty when there is no match
+    Left,
+    /// Every row of both sides
+    Outer,
+}
+
+impl FromStr for JoinKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "inner" => Ok(JoinKind::Inner),
+            "left" => Ok(JoinKind::Left),
+            "outer" | "full" => Ok(JoinKind::Outer),
+            v => anyhow::bail!("Unsupported join: {}", v),
+        }
+    }
+}
+
+pub fn process_csv_join(
+    left: &str,
+    right: &str,
+    on: &[String],
+    kind: JoinKind,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let output = BufWriter::new(get_writer(output)?);
+    join_records(
+        config.open(left)?,
+        config.open(right)?,
+        on,
+        kind,
+        config,
+        output,
+        format,
+    )?;
+    Ok(())
+}
+
+/// Hash join: the right file is indexed by key in memory, the left file is streamed.
+///
+/// The output has every left column followed by the right columns that are not keys.
+pub fn join_records<L: Read, R: Read, W: Write + Send>(
+    mut left: Reader<L>,
+    mut right: Reader<R>,
+    on: &[String],
+    kind: JoinKind,
+    config: CsvReaderConfig,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<usize> {
+    let left_header = config.headers(&mut left)?;
+    let right_header = config.headers(&mut right)?;
+    let left_keys = resolve_columns(&left_header, on)?;
+    let right_keys = resolve_columns(&right_header, on)?;
+    let right_columns = (0..right_header.len())
+        .filter(|i| !right_keys.contains(i))
+        .collect::<Vec<_>>();
+
+    let right_records = right.records().collect::<Result<Vec<_>, _>>()?;
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for (i, record) in right_records.iter().enumerate() {
+        index
+            .entry(record_key(record, &right_keys))
+            .or_default()
+            .push(i);
+    }
+    let mut matched = vec![false; right_records.len()];
+
+    let names = left_header
+        .iter()
+        .chain(right_columns.iter().map(|&i| &right_header[i]));
+    let headers = unique_names(names);
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    let joined = |left: Option<&StringRecord>, right: Option<&StringRecord>| {
+        let left_cells = (0..left_header.len()).map(|i| left.and_then(|r| r.get(i)));
+        let right_cells = right_columns.iter().map(|&i| right.and_then(|r| r.get(i)));
+        string_record(&headers, left_cells.chain(right_cells))
+    };
+
+    for record in left.records() {
+        let record = record?;
+        match index.get(&record_key(&record, &left_keys)) {
+            Some(rows) => {
+                for &i in rows {
+                    matched[i] = true;
+                    writer.write(&joined(Some(&record), Some(&right_records[i])))?;
+                }
+            }
+            None if kind != JoinKind::Inner => writer.write(&joined(Some(&record), None))?,
+            None => {}
+        }
+    }
+
+    if kind == JoinKind::Outer {
+        for (record, _) in right_records.iter().zip(matched).filter(|(_, m)| !m) {
+            // an unmatched right row still fills in the key columns on the left side
+            let left_cells = (0..left_header.len()).map(|i| {
+                let key = left_keys.iter().position(|&k| k == i)?;
+                record.get(right_keys[key])
+            });
+            let right_cells = right_columns.iter().map(|&i| record.get(i));
+            writer.write(&string_record(&headers, left_cells.chain(right_cells)))?;
+        }
+    }
+
+    let count = writer.count();
+    writer.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYERS: &str = "Name,Nationality\nBuffon,Italy\nRonaldo,Portugal\nSzczesny,Poland\n";
+    const COUNTRIES: &str =
+        "Nationality,Continent\nItaly,Europe\nPortugal,Europe\nBrazil,South America\n";
+
+    fn join(kind: JoinKind) -> anyhow::Result<String> {
+        let config = CsvReaderConfig::default();
+        let mut out = Vec::new();
+        join_records(
+            config.reader(PLAYERS.as_bytes()),
+            config.reader(COUNTRIES.as_bytes()),
+            &["Nationality".to_string()],
+            kind,
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_joins() -> anyhow::Result<()> {
+        assert_eq!(
+            join(JoinKind::Inner)?,
+            "Name,Nationality,Continent\nBuffon,Italy,Europe\nRonaldo,Portugal,Europe\n"
+        );
+        assert_eq!(
+            join(JoinKind::Left)?,
+            "Name,Nationality,Continent\nBuffon,Italy,Europe\nRonaldo,Portugal,Europe\nSzczesny,Poland,\n"
+        );
+        assert_eq!(
+            join(JoinKind::Outer)?,
+            "Name,Nationality,Continent\nBuffon,Italy,Europe\nRonaldo,Portugal,Europe\nSzczesny,Poland,\n,Brazil,South America\n"
+        );
+        Ok(())
+    }
+}
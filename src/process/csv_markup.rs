@@ -0,0 +1,189 @@
+use crate::OutputFormat;
+use std::io::{Result, Write};
+
+/// Start of a markdown, html or latex table, up to and including the header row
+pub(crate) fn write_header(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    headers: &[String],
+) -> Result<()> {
+    match format {
+        OutputFormat::Markdown => {
+            write_markdown_row(writer, headers.iter().map(String::as_str))?;
+            writeln!(writer, "|{}", "---|".repeat(headers.len()))
+        }
+        OutputFormat::Html => {
+            writeln!(writer, "<!DOCTYPE html>")?;
+            writeln!(writer, "<html>")?;
+            writeln!(writer, "<head>\n<meta charset=\"utf-8\">\n</head>")?;
+            writeln!(writer, "<body>\n<table>\n<thead>")?;
+            write_html_row(writer, "th", headers.iter().map(String::as_str))?;
+            writeln!(writer, "</thead>\n<tbody>")
+        }
+        OutputFormat::Latex => {
+            writeln!(
+                writer,
+                "\\begin{{tabular}}{{{}}}",
+                "l".repeat(headers.len())
+            )?;
+            writeln!(writer, "\\hline")?;
+            write_latex_row(writer, headers.iter().map(String::as_str))?;
+            writeln!(writer, "\\hline")
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn write_row<'a>(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    cells: impl Iterator<Item = &'a str>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Markdown => write_markdown_row(writer, cells),
+        OutputFormat::Html => write_html_row(writer, "td", cells),
+        OutputFormat::Latex => write_latex_row(writer, cells),
+        _ => Ok(()),
+    }
+}
+
+/// Close the table opened by `write_header`
+pub(crate) fn write_footer(writer: &mut impl Write, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Html => writeln!(writer, "</tbody>\n</table>\n</body>\n</html>"),
+        OutputFormat::Latex => writeln!(writer, "\\hline\n\\end{{tabular}}"),
+        _ => Ok(()),
+    }
+}
+
+fn write_markdown_row<'a>(
+    writer: &mut impl Write,
+    cells: impl Iterator<Item = &'a str>,
+) -> Result<()> {
+    write!(writer, "|")?;
+    for cell in cells {
+        write!(writer, " {} |", escape_markdown(cell))?;
+    }
+    writeln!(writer)
+}
+
+fn write_html_row<'a>(
+    writer: &mut impl Write,
+    tag: &str,
+    cells: impl Iterator<Item = &'a str>,
+) -> Result<()> {
+    write!(writer, "<tr>")?;
+    for cell in cells {
+        write!(writer, "<{}>{}</{}>", tag, escape_html(cell), tag)?;
+    }
+    writeln!(writer, "</tr>")
+}
+
+fn write_latex_row<'a>(
+    writer: &mut impl Write,
+    cells: impl Iterator<Item = &'a str>,
+) -> Result<()> {
+    let cells = cells.map(escape_latex).collect::<Vec<_>>();
+    writeln!(writer, "{} \\\\", cells.join(" & "))
+}
+
+/// Backslash escape anything markdown would render as markup, a pipe would even end the cell.
+/// Newlines would end the row, so they become line breaks.
+fn escape_markdown(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.replace("\r\n", "\n").chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("<br>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_html(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_latex(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            // these come out as other glyphs in the default font encoding
+            '<' => escaped.push_str("\\textless{}"),
+            '>' => escaped.push_str("\\textgreater{}"),
+            '|' => escaped.push_str("\\textbar{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::process::csv_writer::serialize_records;
+    use crate::OutputFormat;
+    use serde_json::json;
+
+    fn render(format: OutputFormat) -> anyhow::Result<String> {
+        let headers = vec!["Name".to_string(), "Note".to_string()];
+        let records = vec![
+            json!({"Name": "Buffon", "Note": "a|b <c> & 100% _x_"}),
+            json!({"Name": "Perin", "Note": null}),
+        ];
+        Ok(String::from_utf8(serialize_records(
+            &headers, &records, format,
+        )?)?)
+    }
+
+    #[test]
+    fn test_markdown() -> anyhow::Result<()> {
+        assert_eq!(
+            render(OutputFormat::Markdown)?,
+            "| Name | Note |\n|---|---|\n| Buffon | a\\|b \\<c\\> & 100% \\_x\\_ |\n| Perin |  |\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_html() -> anyhow::Result<()> {
+        let html = render(OutputFormat::Html)?;
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<tr><th>Name</th><th>Note</th></tr>\n"));
+        assert!(html.contains("<tr><td>Buffon</td><td>a|b &lt;c&gt; &amp; 100% _x_</td></tr>\n"));
+        assert!(html.ends_with("</tbody>\n</table>\n</body>\n</html>\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_latex() -> anyhow::Result<()> {
+        assert_eq!(
+            render(OutputFormat::Latex)?,
+            "\\begin{tabular}{ll}\n\\hline\nName & Note \\\\\n\\hline\n\
+             Buffon & a\\textbar{}b \\textless{}c\\textgreater{} \\& 100\\% \\_x\\_ \\\\\nPerin &  \\\\\n\\hline\n\\end{tabular}\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use crate::process::csv_convert::{typed_record, CsvReaderConfig};
+use crate::process::csv_types::{infer_column_types, ColumnType};
+use crate::process::csv_writer::RecordWriter;
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::Reader;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+/// Load every input file as a table of an in-memory SQLite database and run the query
+pub fn process_csv_query(
+    inputs: &[String],
+    sql: &str,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let mut conn = Connection::open_in_memory()?;
+    for input in inputs {
+        let table = table_name(input);
+        let reader = config.open(input)?;
+        load_table(&mut conn, &table, reader, config)?;
+    }
+
+    let output = BufWriter::new(get_writer(output)?);
+    run_query(&conn, sql, output, format)?;
+    Ok(())
+}
+
+/// Table name for an input file: its file stem with anything but letters,
+/// digits and `_` replaced, `stdin` for `-`
+pub fn table_name(input: &str) -> String {
+    if input == "-" {
+        return "stdin".to_string();
+    }
+    let stem = Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("t_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Create a table with inferred column types and insert all records in one transaction
+pub fn load_table<R: Read>(
+    conn: &mut Connection,
+    table: &str,
+    mut reader: Reader<R>,
+    config: CsvReaderConfig,
+) -> anyhow::Result<usize> {
+    let header = config.headers(&mut reader)?;
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    let types = infer_column_types(header.len(), &records);
+
+    let tx = conn.transaction()?;
+    create_table(&tx, table, &header, &types, false)?;
+    let rows = (config.first_row()..).zip(&records);
+    insert_records(&tx, table, &header, &types, rows)?;
+    tx.commit()?;
+    Ok(records.len())
+}
+
+/// Create a table with a column per header name, typed by the sqlite affinity of its column type
+pub(crate) fn create_table(
+    conn: &Connection,
+    table: &str,
+    header: &csv::StringRecord,
+    types: &[ColumnType],
+    if_not_exists: bool,
+) -> anyhow::Result<()> {
+    let columns = header
+        .iter()
+        .zip(types)
+        .map(|(name, ty)| format!("{} {}", quote_identifier(name), sql_type(*ty)))
+        .collect::<Vec<_>>();
+    conn.execute(
+        &format!(
+            "CREATE TABLE {}{} ({})",
+            if if_not_exists { "IF NOT EXISTS " } else { "" },
+            quote_identifier(table),
+            columns.join(", ")
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+/// Insert typed records, numbered by their row in the input, with a single prepared statement
+pub(crate) fn insert_records<'a>(
+    conn: &Connection,
+    table: &str,
+    header: &csv::StringRecord,
+    types: &[ColumnType],
+    records: impl IntoIterator<Item = (usize, &'a csv::StringRecord)>,
+) -> anyhow::Result<usize> {
+    let placeholders = vec!["?"; header.len()].join(", ");
+    let columns = header
+        .iter()
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_identifier(table),
+        columns,
+        placeholders
+    ))?;
+    let mut count = 0;
+    for (row, record) in records {
+        let value = typed_record(header, types, record, row)?;
+        // short records leave the missing columns null
+        let params = header.iter().map(|name| to_sql_value(&value[name]));
+        stmt.execute(params_from_iter(params))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Run a query and write every result row through the record writer
+pub fn run_query<W: Write + Send>(
+    conn: &Connection,
+    sql: &str,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<usize> {
+    let mut stmt = conn.prepare(sql)?;
+    let headers = unique_names(stmt.column_names());
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut map = Map::new();
+        for (i, name) in headers.iter().enumerate() {
+            map.insert(name.clone(), from_sql_value(row.get_ref(i)?));
+        }
+        writer.write(&Value::Object(map))?;
+    }
+    let count = writer.count();
+    writer.finish()?;
+    Ok(count)
+}
+
+pub(crate) fn sql_type(ty: ColumnType) -> &'static str {
+    match ty {
+        ColumnType::Boolean | ColumnType::Integer => "INTEGER",
+        ColumnType::Float => "REAL",
+        ColumnType::Null | ColumnType::Date | ColumnType::String => "TEXT",
+    }
+}
+
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        v => SqlValue::Text(v.to_string()),
+    }
+}
+
+fn from_sql_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(t) | ValueRef::Blob(t) => {
+            Value::String(String::from_utf8_lossy(t).to_string())
+        }
+    }
+}
+
+/// Result columns may repeat (e.g. `SELECT *` over a join), suffix repeats with `_2`, `_3`, ...
+pub(crate) fn unique_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let mut unique = name.to_string();
+            let mut n = 1;
+            while !seen.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}_{}", name, n);
+            }
+            unique
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_name() {
+        assert_eq!(table_name("assets/juventus.csv"), "juventus");
+        assert_eq!(table_name("2024-roster.v2.csv"), "t_2024_roster_v2");
+        assert_eq!(table_name("-"), "stdin");
+    }
+
+    #[test]
+    fn test_query_group_by_and_join() -> anyhow::Result<()> {
+        let config = CsvReaderConfig::default();
+        let mut conn = Connection::open_in_memory()?;
+        let reader = config.open("assets/juventus.csv")?;
+        load_table(&mut conn, "juventus", reader, config)?;
+        let countries = "Nationality,Continent\nItaly,Europe\nBrazil,South America\n";
+        load_table(
+            &mut conn,
+            "countries",
+            config.reader(countries.as_bytes()),
+            config,
+        )?;
+
+        let sql = "SELECT c.Continent, count(*) AS players, max(j.\"Kit Number\") AS top \
+                   FROM juventus j JOIN countries c ON j.Nationality = c.Nationality \
+                   GROUP BY c.Continent ORDER BY players DESC";
+        let mut out = Vec::new();
+        let count = run_query(&conn, sql, &mut out, OutputFormat::Csv)?;
+        assert_eq!(count, 2);
+        let out = String::from_utf8(out)?;
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("Continent,players,top"));
+        assert!(lines.next().unwrap().starts_with("Europe,"));
+        Ok(())
+    }
+}
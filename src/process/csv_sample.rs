@@ -0,0 +1,146 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_query::unique_names;
+use crate::process::csv_writer::{string_record, RecordWriter};
+use crate::utils::get_writer;
+use crate::OutputFormat;
+use csv::Reader;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{BufWriter, Read, Write};
+
+#[derive(Debug, Clone, Copy)]
+pub enum SampleMethod {
+    /// exactly this many rows (or all of them), held in memory
+    Reservoir(usize),
+    /// every row independently with this probability, streamed
+    Uniform(f64),
+}
+
+/// Write a random sample of the rows, in the order they appear in the input.
+///
+/// The same seed always picks the same rows.
+pub fn process_csv_sample(
+    input: &str,
+    method: SampleMethod,
+    seed: Option<u64>,
+    output: &str,
+    format: OutputFormat,
+    config: CsvReaderConfig,
+) -> anyhow::Result<()> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let output = BufWriter::new(get_writer(output)?);
+    sample_records(
+        config.open(input)?,
+        method,
+        &mut rng,
+        config,
+        output,
+        format,
+    )?;
+    Ok(())
+}
+
+/// Returns the number of rows in the sample
+pub fn sample_records<R: Read, W: Write + Send>(
+    mut reader: Reader<R>,
+    method: SampleMethod,
+    rng: &mut impl Rng,
+    config: CsvReaderConfig,
+    output: W,
+    format: OutputFormat,
+) -> anyhow::Result<usize> {
+    if let SampleMethod::Uniform(p) = method {
+        if !(0.0..=1.0).contains(&p) {
+            anyhow::bail!("Sample fraction must be between 0 and 1, got {}", p);
+        }
+    }
+    let header = config.headers(&mut reader)?;
+    let headers = unique_names(header.iter());
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    match method {
+        SampleMethod::Uniform(p) => {
+            for record in reader.records() {
+                let record = record?;
+                if rng.gen_bool(p) {
+                    writer.write(&string_record(&headers, record.iter().map(Some)))?;
+                }
+            }
+        }
+        SampleMethod::Reservoir(size) => {
+            // algorithm R: row i replaces a random slot with probability size / (i + 1)
+            // the size may be far beyond the number of rows, let the reservoir grow
+            let mut reservoir = Vec::new();
+            for (i, record) in reader.records().enumerate() {
+                let record = record?;
+                if reservoir.len() < size {
+                    reservoir.push((i, record));
+                } else {
+                    let slot = rng.gen_range(0..=i);
+                    if slot < size {
+                        reservoir[slot] = (i, record);
+                    }
+                }
+            }
+            reservoir.sort_by_key(|(i, _)| *i);
+            for (_, record) in &reservoir {
+                writer.write(&string_record(&headers, record.iter().map(Some)))?;
+            }
+        }
+    }
+    let count = writer.count();
+    writer.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(method: SampleMethod, seed: u64) -> anyhow::Result<(usize, String)> {
+        let data = (1..=100).fold("n\n".to_string(), |data, i| data + &format!("{}\n", i));
+        let config = CsvReaderConfig::default();
+        let mut out = Vec::new();
+        let count = sample_records(
+            config.reader(data.as_bytes()),
+            method,
+            &mut StdRng::seed_from_u64(seed),
+            config,
+            &mut out,
+            OutputFormat::Csv,
+        )?;
+        Ok((count, String::from_utf8(out)?))
+    }
+
+    #[test]
+    fn test_reservoir_sample() -> anyhow::Result<()> {
+        let (count, out) = sample(SampleMethod::Reservoir(10), 42)?;
+        assert_eq!(count, 10);
+        let rows = out
+            .lines()
+            .skip(1)
+            .map(|n| n.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(rows.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(sample(SampleMethod::Reservoir(10), 42)?.1, out);
+        assert_ne!(sample(SampleMethod::Reservoir(10), 7)?.1, out);
+        assert_eq!(sample(SampleMethod::Reservoir(1000), 42)?.0, 100);
+        assert_eq!(
+            sample(SampleMethod::Reservoir(1_000_000_000_000), 42)?.0,
+            100
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_uniform_sample() -> anyhow::Result<()> {
+        assert_eq!(sample(SampleMethod::Uniform(1.0), 1)?.0, 100);
+        assert_eq!(sample(SampleMethod::Uniform(0.0), 1)?.0, 0);
+        let (count, _) = sample(SampleMethod::Uniform(0.5), 1)?;
+        assert!((20..80).contains(&count));
+        assert!(sample(SampleMethod::Uniform(1.5), 1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use std::io::{BufWriter, ErrorKind, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Characters used to draw the table borders
+struct Border {
+    horizontal: char,
+    vertical: char,
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+    ellipsis: &'static str,
+}
+
+const UNICODE_BORDER: Border = Border {
+    horizontal: '─',
+    vertical: '│',
+    top: ['┌', '┬', '┐'],
+    middle: ['├', '┼', '┤'],
+    bottom: ['└', '┴', '┘'],
+    ellipsis: "…",
+};
+
+const ASCII_BORDER: Border = Border {
+    horizontal: '-',
+    vertical: '|',
+    top: ['+', '+', '+'],
+    middle: ['+', '+', '+'],
+    bottom: ['+', '+', '+'],
+    ellipsis: "...",
+};
+
+/// How a table is rendered
+#[derive(Debug, Clone, Copy)]
+pub struct TableStyle {
+    /// Cells wider than this are truncated
+    pub max_width: usize,
+    /// Draw the borders with plain ascii instead of box drawing characters
+    pub ascii: bool,
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        Self {
+            max_width: 40,
+            ascii: false,
+        }
+    }
+}
+
+pub fn process_csv_show(
+    input: &str,
+    config: CsvReaderConfig,
+    limit: Option<usize>,
+    style: TableStyle,
+) -> anyhow::Result<()> {
+    let mut reader = config.open(input)?;
+    let headers = config
+        .headers(&mut reader)?
+        .iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut rows = vec![];
+    let mut truncated = false;
+    for record in reader.records() {
+        if limit.is_some_and(|limit| rows.len() >= limit) {
+            truncated = true;
+            break;
+        }
+        rows.push(record?.iter().map(String::from).collect::<Vec<_>>());
+    }
+
+    let mut stdout = BufWriter::new(std::io::stdout().lock());
+    let result = render_table(&mut stdout, &headers, &rows, style).and_then(|_| {
+        if truncated {
+            writeln!(stdout, "(first {} rows)", rows.len())?;
+        }
+        stdout.flush()
+    });
+    match result {
+        // the pager (or `head`) quit before reading everything, that's fine
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => Ok(r?),
+    }
+}
+
+/// Render rows as an aligned table, numbers are right aligned
+pub fn render_table(
+    writer: &mut impl Write,
+    headers: &[String],
+    rows: &[Vec<String>],
+    style: TableStyle,
+) -> std::io::Result<()> {
+    let border = if style.ascii {
+        &ASCII_BORDER
+    } else {
+        &UNICODE_BORDER
+    };
+    let fit = |cell: &str| truncate(cell, style.max_width, border.ellipsis);
+
+    let headers = headers.iter().map(|h| fit(h)).collect::<Vec<_>>();
+    let rows = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| fit(cell)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(headers.len()))
+        .max()
+        .unwrap_or(0);
+    let mut widths = vec![0; columns];
+    for row in rows.iter().chain(std::iter::once(&headers)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    write_rule(writer, &widths, border, border.top)?;
+    write_row(writer, &widths, border, &headers)?;
+    write_rule(writer, &widths, border, border.middle)?;
+    for row in &rows {
+        write_row(writer, &widths, border, row)?;
+    }
+    write_rule(writer, &widths, border, border.bottom)
+}
+
+fn write_rule(
+    writer: &mut impl Write,
+    widths: &[usize],
+    border: &Border,
+    [left, cross, right]: [char; 3],
+) -> std::io::Result<()> {
+    let line = widths
+        .iter()
+        .map(|w| border.horizontal.to_string().repeat(w + 2))
+        .collect::<Vec<_>>()
+        .join(&cross.to_string());
+    writeln!(writer, "{}{}{}", left, line, right)
+}
+
+fn write_row(
+    writer: &mut impl Write,
+    widths: &[usize],
+    border: &Border,
+    row: &[String],
+) -> std::io::Result<()> {
+    write!(writer, "{}", border.vertical)?;
+    for (i, width) in widths.iter().enumerate() {
+        let cell = row.get(i).map(String::as_str).unwrap_or_default();
+        let pad = " ".repeat(width - cell.width());
+        if cell.trim().parse::<f64>().is_ok() {
+            write!(writer, " {}{} {}", pad, cell, border.vertical)?;
+        } else {
+            write!(writer, " {}{} {}", cell, pad, border.vertical)?;
+        }
+    }
+    writeln!(writer)
+}
+
+/// Cut a cell down to `max_width` display columns, line breaks are flattened
+fn truncate(cell: &str, max_width: usize, ellipsis: &str) -> String {
+    let cell = cell.replace(['\r', '\n', '\t'], " ");
+    if cell.width() <= max_width {
+        return cell;
+    }
+    let budget = max_width.saturating_sub(ellipsis.width());
+    let mut out = String::new();
+    let mut width = 0;
+    for c in cell.chars() {
+        let w = c.width().unwrap_or(0);
+        if width + w > budget {
+            break;
+        }
+        width += w;
+        out.push(c);
+    }
+    out.push_str(ellipsis);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table() -> anyhow::Result<()> {
+        let headers = vec!["Name".to_string(), "Kit Number".to_string()];
+        let rows = vec![
+            vec!["Gianluigi Buffon".to_string(), "77".to_string()],
+            vec!["Perin".to_string(), "1".to_string()],
+        ];
+        let style = TableStyle {
+            max_width: 10,
+            ascii: true,
+        };
+        let mut out = Vec::new();
+        render_table(&mut out, &headers, &rows, style)?;
+        let expected = "\
++------------+------------+
+| Name       | Kit Number |
++------------+------------+
+| Gianlui... |         77 |
+| Perin      |          1 |
++------------+------------+
+";
+        assert_eq!(String::from_utf8(out)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_wide_chars() {
+        assert_eq!(truncate("尤文图斯", 5, "…"), "尤文…");
+        assert_eq!(truncate("Juve", 5, "…"), "Juve");
+    }
+}
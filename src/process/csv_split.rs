@@ -0,0 +1,290 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_filter::resolve_columns;
+use csv::{Reader, StringRecord, WriterBuilder};
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// How rows are distributed over the shards
+#[derive(Debug, Clone)]
+pub enum SplitBy {
+    /// at most this many rows per shard
+    Rows(usize),
+    /// at most this many bytes per shard, header included; a shard always gets one row
+    Bytes(u64),
+    /// one shard per distinct value of the column
+    Column(String),
+}
+
+/// Files kept open when splitting by column, the least recently written one is closed
+/// (and reopened for appending when needed again) beyond that
+const MAX_OPEN_SHARDS: usize = 64;
+
+struct Shard {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rows: usize,
+    bytes: u64,
+}
+
+/// Write shards of the input into `out_dir`, each with the header row of the input.
+///
+/// Returns the paths of the shards in the order they were created.
+pub fn process_csv_split(
+    input: &str,
+    by: &SplitBy,
+    out_dir: &str,
+    config: CsvReaderConfig,
+) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(out_dir)?;
+    let stem = match input {
+        "-" => "stdin".to_string(),
+        input => Path::new(input)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "shard".to_string()),
+    };
+    let reader = config.open(input)?;
+    split_records(reader, by, config, |name| {
+        Path::new(out_dir).join(format!("{}-{}.csv", stem, name))
+    })
+}
+
+fn split_records<R: Read>(
+    mut reader: Reader<R>,
+    by: &SplitBy,
+    config: CsvReaderConfig,
+    shard_path: impl Fn(&str) -> PathBuf,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let header = config.headers(&mut reader)?;
+    let header_bytes = if config.header {
+        encode_record(&header, config.delimiter)?
+    } else {
+        vec![]
+    };
+    let column = match by {
+        SplitBy::Rows(0) => anyhow::bail!("Shards need at least one row"),
+        SplitBy::Bytes(0) => anyhow::bail!("Shards need at least one byte"),
+        SplitBy::Column(column) => Some(resolve_columns(&header, std::slice::from_ref(column))?[0]),
+        _ => None,
+    };
+
+    let mut paths = vec![];
+    let mut names = HashSet::new();
+    let mut open = |base: String| -> anyhow::Result<Shard> {
+        let mut name = base.clone();
+        let mut n = 1;
+        while !names.insert(name.clone()) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        let path = shard_path(&name);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writer.write_all(&header_bytes)?;
+        paths.push(path.clone());
+        Ok(Shard {
+            path,
+            writer,
+            rows: 0,
+            bytes: header_bytes.len() as u64,
+        })
+    };
+
+    // numbered shards are written one after the other, value shards are kept open
+    // in least recently written order
+    let mut current: Option<Shard> = None;
+    let mut numbered = 0;
+    let mut value_paths: HashMap<String, PathBuf> = HashMap::new();
+    let mut open_values: IndexMap<String, BufWriter<File>> = IndexMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let row = encode_record(&record, config.delimiter)?;
+        if let Some(column) = column {
+            let value = record.get(column).unwrap_or_default();
+            let writer = match open_values.shift_remove(value) {
+                Some(writer) => writer,
+                None => {
+                    if open_values.len() >= MAX_OPEN_SHARDS {
+                        let (value, writer) = open_values
+                            .shift_remove_index(0)
+                            .expect("open shards aren't empty");
+                        close(&value_paths[&value], writer)?;
+                    }
+                    match value_paths.get(value) {
+                        Some(path) => BufWriter::new(OpenOptions::new().append(true).open(path)?),
+                        None => {
+                            let shard = open(file_name(value))?;
+                            value_paths.insert(value.to_string(), shard.path);
+                            shard.writer
+                        }
+                    }
+                }
+            };
+            open_values
+                .entry(value.to_string())
+                .or_insert(writer)
+                .write_all(&row)?;
+            continue;
+        }
+
+        let full = current.as_ref().is_some_and(|shard| match by {
+            SplitBy::Rows(rows) => shard.rows >= *rows,
+            SplitBy::Bytes(bytes) => shard.rows > 0 && shard.bytes + row.len() as u64 > *bytes,
+            SplitBy::Column(_) => false,
+        });
+        if full || current.is_none() {
+            if let Some(shard) = current.take() {
+                close(&shard.path, shard.writer)?;
+            }
+            numbered += 1;
+            current = Some(open(format!("{:04}", numbered))?);
+        }
+        let shard = current.as_mut().expect("shard was just opened");
+        shard.writer.write_all(&row)?;
+        shard.rows += 1;
+        shard.bytes += row.len() as u64;
+    }
+    if let Some(shard) = current {
+        close(&shard.path, shard.writer)?;
+    }
+    for (value, writer) in open_values {
+        close(&value_paths[&value], writer)?;
+    }
+    Ok(paths)
+}
+
+fn close(path: &Path, mut writer: BufWriter<File>) -> anyhow::Result<()> {
+    writer
+        .flush()
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// A record as it appears in a csv file, line ending included
+fn encode_record(record: &StringRecord, delimiter: u8) -> anyhow::Result<Vec<u8>> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    writer.write_record(record)?;
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Part of a file name for a column value, anything but ascii alphanumerics, `-` and `.`
+/// becomes `_`
+fn file_name(value: &str) -> String {
+    if value.is_empty() {
+        return "empty".to_string();
+    }
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Byte size like `512`, `64K`, `10MB` or `1GiB`, units are powers of 1024
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size: {}", s))?;
+    let shift = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => anyhow::bail!("Invalid size unit: {}", unit),
+    };
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| anyhow::anyhow!("Size too large: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "Name,Nationality\nBuffon,Italy\nSzczesny,Poland\nPerin,Italy\n";
+
+    fn split(by: SplitBy) -> anyhow::Result<Vec<(String, String)>> {
+        let dir = std::env::temp_dir().join(format!("rcli-split-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let config = CsvReaderConfig::default();
+        let paths = split_records(config.reader(DATA.as_bytes()), &by, config, |name| {
+            dir.join(format!("{}.csv", name))
+        })?;
+        let shards = paths
+            .iter()
+            .map(|path| {
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                Ok((name, std::fs::read_to_string(path)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>();
+        std::fs::remove_dir_all(&dir)?;
+        shards
+    }
+
+    #[test]
+    fn test_split_records() -> anyhow::Result<()> {
+        let shards = split(SplitBy::Rows(2))?;
+        assert_eq!(shards.len(), 2);
+        assert_eq!(
+            shards[0],
+            (
+                "0001".to_string(),
+                "Name,Nationality\nBuffon,Italy\nSzczesny,Poland\n".to_string()
+            )
+        );
+        assert_eq!(shards[1].1, "Name,Nationality\nPerin,Italy\n");
+
+        // header (17 bytes) plus one row each
+        let shards = split(SplitBy::Bytes(35))?;
+        assert_eq!(shards.len(), 3);
+
+        let shards = split(SplitBy::Column("Nationality".to_string()))?;
+        assert_eq!(shards[0].0, "Italy");
+        assert_eq!(shards[0].1, "Name,Nationality\nBuffon,Italy\nPerin,Italy\n");
+        assert_eq!(shards[1].0, "Poland");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_many_values() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-split-many-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let values = MAX_OPEN_SHARDS * 3;
+        // every value comes back after all the others, so its shard was closed meanwhile
+        let data = (0..values * 2).fold("n,v\n".to_string(), |data, i| {
+            data + &format!("{},{}\n", i, i % values)
+        });
+        let config = CsvReaderConfig::default();
+        let by = SplitBy::Column("v".to_string());
+        let paths = split_records(config.reader(data.as_bytes()), &by, config, |name| {
+            dir.join(format!("{}.csv", name))
+        })?;
+        assert_eq!(paths.len(), values);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("7.csv"))?,
+            format!("n,v\n7,7\n{},7\n", values + 7)
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_size() -> anyhow::Result<()> {
+        assert_eq!(parse_size("512")?, 512);
+        assert_eq!(parse_size("64K")?, 64 * 1024);
+        assert_eq!(parse_size("10mb")?, 10 * 1024 * 1024);
+        assert!(parse_size("10 parsecs").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::process::csv_convert::{infer_reader_types, ConvertOptions, CsvReaderConfig};
+use crate::process::csv_filter::RecordFilter;
+use crate::process::csv_query::{create_table, insert_records, quote_identifier, table_name};
+use crate::process::csv_types::{merge_record_types, ColumnType};
+use csv::StringRecord;
+use rusqlite::Connection;
+
+/// Rows inserted per statement batch when the records are streamed
+const INSERT_BATCH: usize = 10_000;
+
+/// Write the matching records to a table named after the input file in a sqlite database.
+///
+/// Column types are always inferred, forced types still win. The table is replaced unless
+/// appending, in which case it is only created when missing. Like other conversions, records
+/// are only held in memory when sorting or when the input is stdin, otherwise the types are
+/// inferred in a first pass and the records streamed in batches.
+pub fn export_sqlite(
+    input: &str,
+    output: &str,
+    config: CsvReaderConfig,
+    options: &ConvertOptions,
+) -> anyhow::Result<usize> {
+    if output == "-" {
+        anyhow::bail!("sqlite output needs a database file");
+    }
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let filter = RecordFilter::new(&header, &options.select, &options.filters, &options.sort_by)?;
+    let out_header = filter.project(&header);
+
+    let buffered = filter.needs_sort() || input == "-";
+    let mut records = vec![];
+    let inferred = if buffered {
+        let mut inferred = vec![ColumnType::Null; out_header.len()];
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            if filter.matches(&record) {
+                merge_record_types(&mut inferred, &filter.project(&record));
+                records.push((config.first_row() + i, record));
+            }
+        }
+        filter.sort(&mut records, |(_, record)| record);
+        inferred
+    } else {
+        let mut first_pass = config.open(input)?;
+        config.headers(&mut first_pass)?;
+        infer_reader_types(&mut first_pass, &filter, out_header.len())?
+    };
+    let types = options.column_types(&out_header, Some(inferred));
+
+    let table = table_name(input);
+    let mut conn = Connection::open(output)?;
+    let tx = conn.transaction()?;
+    if !options.append {
+        tx.execute(
+            &format!("DROP TABLE IF EXISTS {}", quote_identifier(&table)),
+            [],
+        )?;
+    }
+    create_table(&tx, &table, &out_header, &types, true)?;
+    let insert = |batch: &[(usize, StringRecord)]| {
+        let rows = batch.iter().map(|(row, record)| (*row, record));
+        insert_records(&tx, &table, &out_header, &types, rows)
+    };
+    let count = if buffered {
+        let records = records
+            .into_iter()
+            .map(|(row, record)| (row, filter.project(&record)))
+            .collect::<Vec<_>>();
+        insert(&records)?
+    } else {
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(INSERT_BATCH);
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            if filter.matches(&record) {
+                batch.push((config.first_row() + i, filter.project(&record)));
+            }
+            if batch.len() == INSERT_BATCH {
+                count += insert(&batch)?;
+                batch.clear();
+            }
+        }
+        count + insert(&batch)?
+    };
+    tx.commit()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_sqlite() -> anyhow::Result<()> {
+        let db = std::env::temp_dir().join(format!("rcli-export-{}.sqlite", std::process::id()));
+        let db = db.to_string_lossy().to_string();
+        let config = CsvReaderConfig::default();
+        let mut options = ConvertOptions {
+            filters: vec!["Nationality == Italy".to_string()],
+            ..Default::default()
+        };
+
+        let count = |conn: &Connection| -> rusqlite::Result<i64> {
+            conn.query_row("SELECT count(*) FROM juventus", [], |row| row.get(0))
+        };
+        assert_eq!(
+            export_sqlite("assets/juventus.csv", &db, config, &options)?,
+            8
+        );
+        assert_eq!(
+            export_sqlite("assets/juventus.csv", &db, config, &options)?,
+            8
+        );
+        assert_eq!(count(&Connection::open(&db)?)?, 8);
+
+        options.append = true;
+        export_sqlite("assets/juventus.csv", &db, config, &options)?;
+        let conn = Connection::open(&db)?;
+        assert_eq!(count(&conn)?, 16);
+        let kit_type: String = conn.query_row(
+            "SELECT typeof(\"Kit Number\") FROM juventus LIMIT 1",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(kit_type, "integer");
+
+        std::fs::remove_file(&db)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_sqlite_batches() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-export-batches-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let csv = dir.join("rows.csv");
+        let rows = INSERT_BATCH * 2 + 7;
+        let data = (0..rows).fold("n,even\n".to_string(), |data, i| {
+            data + &format!("{},{}\n", i, i % 2 == 0)
+        });
+        std::fs::write(&csv, data)?;
+        let db = dir.join("rows.sqlite").to_string_lossy().to_string();
+
+        let options = ConvertOptions::default();
+        let count = export_sqlite(
+            &csv.to_string_lossy(),
+            &db,
+            CsvReaderConfig::default(),
+            &options,
+        )?;
+        assert_eq!(count, rows);
+        let conn = Connection::open(&db)?;
+        let (count, sum): (usize, i64) =
+            conn.query_row("SELECT count(*), sum(n) FROM rows", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+        assert_eq!(count, rows);
+        assert_eq!(sum as usize, rows * (rows - 1) / 2);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
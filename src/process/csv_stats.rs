@@ -0,0 +1,259 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_show::{render_table, TableStyle};
+use crate::process::csv_types::ColumnType;
+use crate::process::csv_writer::value_to_cell;
+use csv::StringRecord;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Profile of a single csv column
+#[derive(Debug, Serialize)]
+pub struct ColumnStats {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+    pub count: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    pub top: Vec<TopValue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Running statistics of a column, updated one cell at a time
+#[derive(Debug)]
+struct ColumnProfile {
+    column_type: ColumnType,
+    count: usize,
+    nulls: usize,
+    frequencies: HashMap<String, usize>,
+    min_text: Option<String>,
+    max_text: Option<String>,
+    min_number: f64,
+    max_number: f64,
+    // Welford's online mean and variance
+    numbers: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl Default for ColumnProfile {
+    fn default() -> Self {
+        Self {
+            column_type: ColumnType::Null,
+            count: 0,
+            nulls: 0,
+            frequencies: HashMap::new(),
+            min_text: None,
+            max_text: None,
+            min_number: f64::INFINITY,
+            max_number: f64::NEG_INFINITY,
+            numbers: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+impl ColumnProfile {
+    fn add(&mut self, cell: &str) {
+        self.count += 1;
+        let ty = ColumnType::detect(cell);
+        self.column_type = self.column_type.merge(ty);
+        if ty == ColumnType::Null {
+            self.nulls += 1;
+            return;
+        }
+        *self.frequencies.entry(cell.to_string()).or_default() += 1;
+        if self.min_text.as_deref().is_none_or(|min| cell < min) {
+            self.min_text = Some(cell.to_string());
+        }
+        if self.max_text.as_deref().is_none_or(|max| cell > max) {
+            self.max_text = Some(cell.to_string());
+        }
+        if let Ok(n) = cell.trim().parse::<f64>() {
+            self.min_number = self.min_number.min(n);
+            self.max_number = self.max_number.max(n);
+            self.numbers += 1;
+            let delta = n - self.mean;
+            self.mean += delta / self.numbers as f64;
+            self.m2 += delta * (n - self.mean);
+        }
+    }
+
+    fn finish(self, name: &str, top: usize) -> ColumnStats {
+        let numeric = matches!(self.column_type, ColumnType::Integer | ColumnType::Float);
+        let (min, max, mean, stddev) = if numeric {
+            let stddev = (self.numbers > 1).then(|| (self.m2 / (self.numbers - 1) as f64).sqrt());
+            (
+                Some(number(self.min_number)),
+                Some(number(self.max_number)),
+                Some(self.mean),
+                stddev,
+            )
+        } else {
+            (
+                self.min_text.map(Value::String),
+                self.max_text.map(Value::String),
+                None,
+                None,
+            )
+        };
+
+        let distinct = self.frequencies.len();
+        let mut frequencies = self.frequencies.into_iter().collect::<Vec<_>>();
+        // most frequent first, ties in value order so the output is stable
+        frequencies.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+        let top = frequencies
+            .into_iter()
+            .take(top)
+            .map(|(value, count)| TopValue { value, count })
+            .collect();
+
+        ColumnStats {
+            name: name.to_string(),
+            column_type: self.column_type,
+            count: self.count,
+            nulls: self.nulls,
+            distinct,
+            min,
+            max,
+            mean,
+            stddev,
+            top,
+        }
+    }
+}
+
+/// A whole float as an integer value, so `77.0` is written as `77`
+pub(crate) fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+pub fn process_csv_stats(
+    input: &str,
+    config: CsvReaderConfig,
+    top: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let stats = column_stats(&header, reader.records(), top)?;
+
+    let mut stdout = std::io::stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &stats)?;
+        writeln!(stdout)?;
+    } else {
+        write_stats_table(&mut stdout, &stats)?;
+    }
+    Ok(())
+}
+
+/// Profile every column in a single pass over the records
+pub fn column_stats(
+    header: &StringRecord,
+    records: impl Iterator<Item = csv::Result<StringRecord>>,
+    top: usize,
+) -> anyhow::Result<Vec<ColumnStats>> {
+    let mut profiles = (0..header.len())
+        .map(|_| ColumnProfile::default())
+        .collect::<Vec<_>>();
+    for record in records {
+        let record = record?;
+        for (profile, cell) in profiles.iter_mut().zip(record.iter()) {
+            profile.add(cell);
+        }
+    }
+    Ok(profiles
+        .into_iter()
+        .zip(header.iter())
+        .map(|(profile, name)| profile.finish(name, top))
+        .collect())
+}
+
+fn write_stats_table(writer: &mut impl Write, stats: &[ColumnStats]) -> std::io::Result<()> {
+    let headers = [
+        "column", "type", "count", "nulls", "distinct", "min", "max", "mean", "stddev", "top",
+    ]
+    .map(String::from);
+    let float = |f: Option<f64>| f.map(|f| format!("{:.2}", f)).unwrap_or_default();
+    let rows = stats
+        .iter()
+        .map(|s| {
+            let top = s
+                .top
+                .iter()
+                .map(|t| format!("{} ({})", t.value, t.count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            vec![
+                s.name.clone(),
+                s.column_type.to_string(),
+                s.count.to_string(),
+                s.nulls.to_string(),
+                s.distinct.to_string(),
+                s.min.as_ref().map(value_to_cell).unwrap_or_default(),
+                s.max.as_ref().map(value_to_cell).unwrap_or_default(),
+                float(s.mean),
+                float(s.stddev),
+                top,
+            ]
+        })
+        .collect::<Vec<_>>();
+    render_table(writer, &headers, &rows, TableStyle::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_stats() -> anyhow::Result<()> {
+        let config = CsvReaderConfig::default();
+        let mut reader = config.open("assets/juventus.csv")?;
+        let header = config.headers(&mut reader)?;
+        let stats = column_stats(&header, reader.records(), 2)?;
+
+        let nationality = &stats[3];
+        assert_eq!(nationality.column_type, ColumnType::String);
+        assert_eq!(nationality.top[0].value, "Italy");
+        assert_eq!(nationality.top[0].count, 8);
+        assert!(nationality.mean.is_none());
+
+        let kit = &stats[4];
+        assert_eq!(kit.column_type, ColumnType::Integer);
+        assert_eq!(kit.nulls, 0);
+        assert_eq!(kit.distinct, kit.count);
+        assert_eq!(kit.min, Some(Value::from(1)));
+        assert_eq!(kit.max, Some(Value::from(77)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mean_and_stddev() -> anyhow::Result<()> {
+        let header = StringRecord::from(vec!["score"]);
+        let records = ["2", "4", "", "4", "4", "5", "5", "7", "9"]
+            .map(|cell| Ok(StringRecord::from(vec![cell])));
+        let stats = column_stats(&header, records.into_iter(), 1)?;
+        assert_eq!(stats[0].nulls, 1);
+        assert_eq!(stats[0].mean, Some(5.0));
+        assert!((stats[0].stddev.unwrap() - 2.138).abs() < 1e-3);
+        assert_eq!(stats[0].top[0].value, "4");
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::utils::get_data;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Type of a csv column, ordered from the most to the least specific
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Null,
+    #[serde(alias = "bool")]
+    Boolean,
+    #[serde(alias = "int")]
+    Integer,
+    #[serde(alias = "number")]
+    Float,
+    Date,
+    #[serde(alias = "str", alias = "text")]
+    String,
+}
+
+impl ColumnType {
+    /// Detect the type of a single cell
+    pub fn detect(cell: &str) -> Self {
+        let cell = cell.trim();
+        if is_null(cell) {
+            ColumnType::Null
+        } else if cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false") {
+            ColumnType::Boolean
+        } else if cell.parse::<i64>().is_ok() {
+            ColumnType::Integer
+        } else if cell.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+            ColumnType::Float
+        } else if is_iso_date(cell) {
+            ColumnType::Date
+        } else {
+            ColumnType::String
+        }
+    }
+
+    /// The narrowest type that can hold values of both types
+    pub fn merge(self, other: Self) -> Self {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Null, t) | (t, Null) => t,
+            (Integer, Float) | (Float, Integer) => Float,
+            _ => String,
+        }
+    }
+
+    /// Convert a cell into a typed json value. String columns keep the raw text,
+    /// other columns turn empty or `null` cells into json null.
+    pub fn convert(self, cell: &str) -> anyhow::Result<Value> {
+        if self == ColumnType::String {
+            return Ok(Value::String(cell.to_string()));
+        }
+        let trimmed = cell.trim();
+        if is_null(trimmed) {
+            return Ok(Value::Null);
+        }
+        let value = match self {
+            ColumnType::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ColumnType::Integer => trimmed.parse::<i64>().ok().map(Value::from),
+            ColumnType::Float => trimmed
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            ColumnType::Date => is_iso_date(trimmed).then(|| Value::String(trimmed.to_string())),
+            ColumnType::Null | ColumnType::String => None,
+        };
+        value.ok_or_else(|| anyhow::anyhow!("Cannot convert {:?} to {}", cell, self))
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ColumnType::Null => "null",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::String => "string",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn is_null(cell: &str) -> bool {
+    cell.is_empty() || cell.eq_ignore_ascii_case("null")
+}
+
+fn is_iso_date(cell: &str) -> bool {
+    parse_date(cell).is_some()
+}
+
+/// Parse an iso date or date time, dates are at midnight and offsets are converted to utc
+pub(crate) fn parse_date(cell: &str) -> Option<NaiveDateTime> {
+    NaiveDate::parse_from_str(cell, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .or_else(|| NaiveDateTime::parse_from_str(cell, "%Y-%m-%dT%H:%M:%S%.f").ok())
+        .or_else(|| NaiveDateTime::parse_from_str(cell, "%Y-%m-%d %H:%M:%S%.f").ok())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(cell)
+                .ok()
+                .map(|d| d.naive_utc())
+        })
+}
+
+/// Infer the type of every column from all of its cells
+pub fn infer_column_types<'a>(
+    width: usize,
+    records: impl IntoIterator<Item = &'a StringRecord>,
+) -> Vec<ColumnType> {
+    let mut types = vec![ColumnType::Null; width];
+    for record in records {
+        merge_record_types(&mut types, record);
+    }
+    types
+}
+
+/// Widen the column types to fit the cells of one more record
+pub fn merge_record_types(types: &mut [ColumnType], record: &StringRecord) {
+    for (ty, cell) in types.iter_mut().zip(record.iter()) {
+        *ty = ty.merge(ColumnType::detect(cell));
+    }
+}
+
+/// Load per column type overrides from a yaml (or json) file of `column: type` pairs
+pub fn load_column_types(path: &str) -> anyhow::Result<HashMap<String, ColumnType>> {
+    let reader = get_data(path)?;
+    Ok(serde_yaml::from_reader(reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_merge() {
+        let records = vec![
+            StringRecord::from(vec!["10", "1.5", "true", "", "2024-01-31", "Buffon"]),
+            StringRecord::from(vec!["7", "2", "FALSE", "null", "2024-02-01T10:00:00Z", "7"]),
+        ];
+        assert_eq!(
+            infer_column_types(6, &records),
+            vec![
+                ColumnType::Integer,
+                ColumnType::Float,
+                ColumnType::Boolean,
+                ColumnType::Null,
+                ColumnType::Date,
+                ColumnType::String,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert() -> anyhow::Result<()> {
+        assert_eq!(ColumnType::Integer.convert("10")?, Value::from(10));
+        assert_eq!(ColumnType::Integer.convert("")?, Value::Null);
+        assert_eq!(ColumnType::Float.convert("2")?, Value::from(2.0));
+        assert_eq!(ColumnType::Boolean.convert("True")?, Value::Bool(true));
+        assert_eq!(ColumnType::String.convert("")?, Value::from(""));
+        assert!(ColumnType::Integer.convert("ten").is_err());
+        Ok(())
+    }
+}
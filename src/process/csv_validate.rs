@@ -0,0 +1,283 @@
+use crate::process::csv_convert::CsvReaderConfig;
+use crate::process::csv_types::ColumnType;
+use crate::utils::get_data;
+use csv::StringRecord;
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+/// Constraints a csv file has to satisfy, loaded from yaml
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schema {
+    /// Whether columns not listed in the schema are allowed
+    #[serde(default = "default_true")]
+    pub allow_extra_columns: bool,
+    #[serde(deserialize_with = "ordered_columns")]
+    pub columns: IndexMap<String, ColumnRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnRule {
+    /// The column has to be present in the header
+    #[serde(default = "default_true")]
+    pub required: bool,
+    /// Empty or `null` cells are allowed
+    #[serde(default = "default_true")]
+    pub nullable: bool,
+    #[serde(rename = "type")]
+    pub column_type: Option<ColumnType>,
+    pub pattern: Option<String>,
+    #[serde(skip)]
+    regex: Option<Regex>,
+    #[serde(rename = "enum")]
+    pub allowed: Option<Vec<String>>,
+    #[serde(default)]
+    pub unique: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Keep the columns in file order, so violations are reported in schema order
+pub(crate) fn ordered_columns<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    deserializer: D,
+) -> Result<IndexMap<String, T>, D::Error> {
+    Map::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, rule)| {
+            let rule = T::deserialize(rule)
+                .map_err(|e| D::Error::custom(format!("column {:?}: {}", name, e)))?;
+            Ok((name, rule))
+        })
+        .collect()
+}
+
+/// A single failed constraint, `row` is `None` for problems with the header
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub row: Option<usize>,
+    pub column: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.row {
+            Some(row) => write!(f, "row {}, column {:?}: {}", row, self.column, self.message),
+            None => write!(f, "header, column {:?}: {}", self.column, self.message),
+        }
+    }
+}
+
+impl Schema {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let reader = get_data(path)?;
+        Self::from_reader(reader)
+    }
+
+    pub fn from_reader(reader: impl Read) -> anyhow::Result<Self> {
+        let mut schema: Schema = serde_yaml::from_reader(reader)?;
+        for (name, rule) in schema.columns.iter_mut() {
+            if let Some(pattern) = &rule.pattern {
+                let regex = Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid pattern for column {:?}: {}", name, e))?;
+                rule.regex = Some(regex);
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Check the header and every record, collecting all violations
+    pub fn validate(
+        &self,
+        header: &StringRecord,
+        records: impl Iterator<Item = csv::Result<StringRecord>>,
+        first_row: usize,
+    ) -> anyhow::Result<Vec<Violation>> {
+        let mut violations = vec![];
+        let mut columns = vec![];
+        for (name, rule) in &self.columns {
+            match header.iter().position(|h| h == name) {
+                Some(i) => columns.push((i, name.as_str(), rule)),
+                None if rule.required => violations.push(Violation {
+                    row: None,
+                    column: name.clone(),
+                    message: "required column is missing".to_string(),
+                }),
+                None => {}
+            }
+        }
+        if !self.allow_extra_columns {
+            for name in header.iter().filter(|h| !self.columns.contains_key(*h)) {
+                violations.push(Violation {
+                    row: None,
+                    column: name.to_string(),
+                    message: "column is not in the schema".to_string(),
+                });
+            }
+        }
+
+        // first row each value of a unique column was seen in
+        let mut seen: HashMap<usize, HashMap<String, usize>> = HashMap::new();
+        for (i, record) in records.enumerate() {
+            let record = record?;
+            let row = first_row + i;
+            for &(index, name, rule) in &columns {
+                let cell = record.get(index).unwrap_or_default();
+                let mut violation = |message: String| {
+                    violations.push(Violation {
+                        row: Some(row),
+                        column: name.to_string(),
+                        message,
+                    })
+                };
+                if let Some(message) = rule.check(cell) {
+                    violation(message);
+                }
+                if rule.unique && ColumnType::detect(cell) != ColumnType::Null {
+                    let values = seen.entry(index).or_default();
+                    if let Some(first) = values.get(cell) {
+                        violation(format!(
+                            "duplicate value {:?}, first seen in row {}",
+                            cell, first
+                        ));
+                    } else {
+                        values.insert(cell.to_string(), row);
+                    }
+                }
+            }
+        }
+        Ok(violations)
+    }
+}
+
+impl ColumnRule {
+    /// The first constraint a cell violates, uniqueness is checked by the caller
+    fn check(&self, cell: &str) -> Option<String> {
+        if ColumnType::detect(cell) == ColumnType::Null {
+            return (!self.nullable).then(|| "value is required".to_string());
+        }
+        if let Some(ty) = self.column_type {
+            if ty.convert(cell).is_err() {
+                return Some(format!("{:?} is not a valid {}", cell, ty));
+            }
+        }
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(cell) {
+                return Some(format!("{:?} does not match {}", cell, regex));
+            }
+        }
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|v| v == cell) {
+                return Some(format!("{:?} is not one of {}", cell, allowed.join(", ")));
+            }
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(n) = cell.trim().parse::<f64>() else {
+                return Some(format!("{:?} is not a number", cell));
+            };
+            if self.min.is_some_and(|min| n < min) || self.max.is_some_and(|max| n > max) {
+                let bound = |b: Option<f64>| b.map(|b| b.to_string()).unwrap_or_default();
+                return Some(format!(
+                    "{} is out of range [{}, {}]",
+                    cell,
+                    bound(self.min),
+                    bound(self.max)
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Print every violation and fail when there are any
+pub fn process_csv_validate(
+    input: &str,
+    config: CsvReaderConfig,
+    schema: &str,
+) -> anyhow::Result<()> {
+    let schema = Schema::load(schema)?;
+    let mut reader = config.open(input)?;
+    let header = config.headers(&mut reader)?;
+    let violations = schema.validate(&header, reader.records(), config.first_row())?;
+    for violation in &violations {
+        println!("{}", violation);
+    }
+    if violations.is_empty() {
+        println!("{} is valid", input);
+        Ok(())
+    } else {
+        anyhow::bail!("{} violations found in {}", violations.len(), input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_juventus_schema() -> anyhow::Result<()> {
+        let schema = Schema::load("assets/juventus.schema.yaml")?;
+        let config = CsvReaderConfig::default();
+        let mut reader = config.open("assets/juventus.csv")?;
+        let header = config.headers(&mut reader)?;
+        let violations = schema.validate(&header, reader.records(), config.first_row())?;
+        assert_eq!(violations, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_violations() -> anyhow::Result<()> {
+        let schema = Schema::from_reader(
+            r#"
+allow_extra_columns: false
+columns:
+  Name:
+    nullable: false
+  Position:
+    enum: [Goalkeeper, Defender]
+  Kit Number:
+    type: integer
+    unique: true
+    min: 0
+    max: 255
+  DOB:
+    pattern: '^\d{4}-\d{2}-\d{2}$'
+"#
+            .as_bytes(),
+        )?;
+        let data = "Name,Position,Kit Number,Club\n\
+                    Buffon,Goalkeeper,77,Juventus\n\
+                    ,Striker,300,Juventus\n\
+                    Perin,Goalkeeper,77,Juventus\n\
+                    Rugani,Defender,x,Juventus\n";
+        let config = CsvReaderConfig::default();
+        let mut reader = config.reader(data.as_bytes());
+        let header = config.headers(&mut reader)?;
+        let violations = schema.validate(&header, reader.records(), config.first_row())?;
+        let report = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            report,
+            vec![
+                "header, column \"DOB\": required column is missing",
+                "header, column \"Club\": column is not in the schema",
+                "row 3, column \"Name\": value is required",
+                "row 3, column \"Position\": \"Striker\" is not one of Goalkeeper, Defender",
+                "row 3, column \"Kit Number\": 300 is out of range [0, 255]",
+                "row 4, column \"Kit Number\": duplicate value \"77\", first seen in row 2",
+                "row 5, column \"Kit Number\": \"x\" is not a valid integer",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use crate::process::csv_arrow::{ColumnarWriter, ParquetOptions};
+use crate::process::csv_binary;
+use crate::process::csv_markup;
+use crate::process::csv_types::ColumnType;
+use crate::OutputFormat;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+#[derive(Serialize)]
+struct TomlDocument<'a> {
+    rows: &'a [Value],
+}
+
+/// Writes records (json objects keyed by column name) one at a time,
+/// so the output never has to be held in memory as a whole
+pub struct RecordWriter<W: Write> {
+    format: OutputFormat,
+    headers: Vec<String>,
+    sink: Sink<W>,
+    count: usize,
+}
+
+enum Sink<W: Write> {
+    Text(W),
+    Delimited(Box<csv::Writer<W>>),
+    Columnar(Box<ColumnarWriter<W>>),
+}
+
+impl<W: Write + Send> RecordWriter<W> {
+    pub fn new(writer: W, format: OutputFormat, headers: Vec<String>) -> anyhow::Result<Self> {
+        let sink = match format {
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let delimiter = if matches!(format, OutputFormat::Tsv) {
+                    b'\t'
+                } else {
+                    b','
+                };
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .from_writer(writer);
+                writer.write_record(&headers)?;
+                Sink::Delimited(Box::new(writer))
+            }
+            OutputFormat::Sqlite => {
+                anyhow::bail!("sqlite output is written to a database file, not a stream")
+            }
+            OutputFormat::Parquet | OutputFormat::Arrow => {
+                anyhow::bail!("{} output needs the column types", format)
+            }
+            OutputFormat::Markdown | OutputFormat::Html | OutputFormat::Latex => {
+                let mut writer = writer;
+                csv_markup::write_header(&mut writer, format, &headers)?;
+                Sink::Text(writer)
+            }
+            OutputFormat::Cbor => {
+                let mut writer = writer;
+                csv_binary::start_array(&mut writer, format)?;
+                Sink::Text(writer)
+            }
+            _ => Sink::Text(writer),
+        };
+        Ok(Self {
+            format,
+            headers,
+            sink,
+            count: 0,
+        })
+    }
+
+    /// A writer that also handles the columnar formats, which need a typed schema up front
+    pub fn typed(
+        writer: W,
+        format: OutputFormat,
+        headers: Vec<String>,
+        types: &[ColumnType],
+        parquet: ParquetOptions,
+    ) -> anyhow::Result<Self> {
+        match format {
+            OutputFormat::Parquet | OutputFormat::Arrow => {
+                let writer = ColumnarWriter::new(writer, format, headers.clone(), types, parquet)?;
+                Ok(Self {
+                    format,
+                    headers,
+                    sink: Sink::Columnar(Box::new(writer)),
+                    count: 0,
+                })
+            }
+            _ => Self::new(writer, format, headers),
+        }
+    }
+
+    /// Number of records written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        match &mut self.sink {
+            Sink::Delimited(writer) => {
+                writer.write_record(self.headers.iter().map(|h| value_to_cell(&record[h])))?;
+            }
+            Sink::Columnar(writer) => writer.write(record)?,
+            Sink::Text(writer) => match self.format {
+                OutputFormat::Json => {
+                    // same layout as `serde_json::to_vec_pretty` on the whole array
+                    writer.write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+                    let pretty = serde_json::to_string_pretty(record)?;
+                    for (i, line) in pretty.lines().enumerate() {
+                        if i > 0 {
+                            writer.write_all(b"\n")?;
+                        }
+                        write!(writer, "  {}", line)?;
+                    }
+                }
+                OutputFormat::Yaml => {
+                    // a one element sequence per record concatenates into a single sequence
+                    let item = serde_yaml::to_string(std::slice::from_ref(record))?;
+                    writer.write_all(item.as_bytes())?;
+                }
+                OutputFormat::Toml => {
+                    if self.count > 0 {
+                        writer.write_all(b"\n")?;
+                    }
+                    // toml has no null, leave those keys out
+                    let rows = [without_nulls(record)];
+                    let table = toml::to_string(&TomlDocument { rows: &rows })?;
+                    writer.write_all(table.as_bytes())?;
+                }
+                OutputFormat::Ndjson => {
+                    serde_json::to_writer(&mut *writer, record)?;
+                    writer.write_all(b"\n")?;
+                }
+                OutputFormat::Markdown | OutputFormat::Html | OutputFormat::Latex => {
+                    let cells = self.headers.iter().map(|h| value_to_cell(&record[h]));
+                    let cells = cells.collect::<Vec<_>>();
+                    csv_markup::write_row(writer, self.format, cells.iter().map(String::as_str))?;
+                }
+                OutputFormat::Msgpack | OutputFormat::Cbor => {
+                    csv_binary::write_value(writer, self.format, record)?
+                }
+                OutputFormat::Csv | OutputFormat::Tsv => {
+                    unreachable!("delimited formats use csv::Writer")
+                }
+                OutputFormat::Sqlite | OutputFormat::Parquet | OutputFormat::Arrow => {
+                    unreachable!("{} is not a text format", self.format)
+                }
+            },
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Close any open structure and flush, returning the underlying writer
+    pub fn finish(self) -> anyhow::Result<W> {
+        let mut writer = match self.sink {
+            Sink::Delimited(writer) => writer.into_inner().map_err(|e| e.into_error())?,
+            Sink::Columnar(writer) => writer.finish()?,
+            Sink::Text(mut writer) => {
+                match self.format {
+                    OutputFormat::Json if self.count == 0 => writer.write_all(b"[]\n")?,
+                    OutputFormat::Json => writer.write_all(b"\n]\n")?,
+                    OutputFormat::Yaml if self.count == 0 => writer.write_all(b"[]\n")?,
+                    OutputFormat::Cbor => csv_binary::finish_array(&mut writer, self.format)?,
+                    format => csv_markup::write_footer(&mut writer, format)?,
+                }
+                writer
+            }
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// Serialize records (json objects keyed by column name) into the output format
+pub fn serialize_records(
+    headers: &[String],
+    records: &[Value],
+    format: OutputFormat,
+) -> anyhow::Result<Vec<u8>> {
+    let mut writer = RecordWriter::new(Vec::new(), format, headers.to_vec())?;
+    for record in records {
+        writer.write(record)?;
+    }
+    writer.finish()
+}
+
+fn without_nulls(record: &Value) -> Value {
+    match record {
+        Value::Object(map) => map
+            .iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+        v => v.clone(),
+    }
+}
+
+/// Render a json value as a single csv cell
+pub(crate) fn value_to_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Json object of a record with every cell kept as a string, missing cells are null
+pub(crate) fn string_record<'a>(
+    headers: &[String],
+    cells: impl IntoIterator<Item = Option<&'a str>>,
+) -> Value {
+    let map = headers
+        .iter()
+        .zip(cells)
+        .map(|(h, cell)| (h.clone(), cell.map_or(Value::Null, Value::from)))
+        .collect::<Map<String, Value>>();
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_records() -> anyhow::Result<()> {
+        let headers = vec!["Name".to_string(), "Kit Number".to_string()];
+        let records = vec![
+            json!({"Name": "Buffon", "Kit Number": "77"}),
+            json!({"Name": "Perin", "Kit Number": "37"}),
+        ];
+
+        let tsv = serialize_records(&headers, &records, OutputFormat::Tsv)?;
+        assert_eq!(tsv, b"Name\tKit Number\nBuffon\t77\nPerin\t37\n");
+
+        let ndjson = serialize_records(&headers, &records, OutputFormat::Ndjson)?;
+        assert_eq!(
+            String::from_utf8(ndjson)?,
+            "{\"Name\":\"Buffon\",\"Kit Number\":\"77\"}\n{\"Name\":\"Perin\",\"Kit Number\":\"37\"}\n"
+        );
+
+        let toml = String::from_utf8(serialize_records(&headers, &records, OutputFormat::Toml)?)?;
+        assert!(toml.starts_with("[[rows]]\nName = \"Buffon\"\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_output_matches_whole_document() -> anyhow::Result<()> {
+        let headers = vec!["a".to_string(), "b".to_string()];
+        let records = vec![
+            json!({"a": 1, "b": {"c": [1, 2]}}),
+            json!({"a": null, "b": "x"}),
+        ];
+
+        // the whole document, ended by a newline like the other text formats
+        let json = serialize_records(&headers, &records, OutputFormat::Json)?;
+        let mut expected = serde_json::to_vec_pretty(&records)?;
+        expected.push(b'\n');
+        assert_eq!(json, expected);
+        let yaml = serialize_records(&headers, &records, OutputFormat::Yaml)?;
+        assert_eq!(String::from_utf8(yaml)?, serde_yaml::to_string(&records)?);
+
+        let empty = serialize_records(&headers, &[], OutputFormat::Json)?;
+        assert_eq!(empty, b"[]\n");
+        Ok(())
+    }
+}
@@ -1,61 +1,416 @@
 use rand::prelude::SliceRandom;
+use serde::Serialize;
+use zxcvbn::time_estimates::CrackTimeSeconds;
+use zxcvbn::zxcvbn;
 
-const UPPER: &[u8] = b"ABCDEFGHIJKLMNPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefghijklmnpqrstuvwxyz";
-const NUMBER: &[u8] = b"123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*-_";
-
-pub fn process_genpass(
-    length: usize,
-    uppercase: bool,
-    lowercase: bool,
-    number: bool,
-    symbol: bool,
-) -> anyhow::Result<Vec<u8>> {
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+pub(crate) const NUMBER: &[u8] = b"0123456789";
+pub(crate) const SYMBOL: &[u8] = b"!@#$%^&*-_";
+/// Characters that are easily confused with each other
+const AMBIGUOUS: &[u8] = b"0Oo1lI|";
+
+/// Which characters a password is made of
+#[derive(Debug, Clone)]
+pub struct GenPassOptions {
+    pub length: usize,
+    pub uppercase: bool,
+    pub lowercase: bool,
+    pub number: bool,
+    pub symbol: bool,
+    /// Draw from exactly these characters instead of the classes above, a class is
+    /// used when the charset has characters of it
+    pub charset: Option<String>,
+    /// Never use these characters
+    pub exclude: String,
+    pub exclude_ambiguous: bool,
+    /// Least number of characters of each class, one for every used class by default
+    pub min_upper: Option<usize>,
+    pub min_lower: Option<usize>,
+    pub min_digits: Option<usize>,
+    pub min_symbols: Option<usize>,
+}
+
+impl Default for GenPassOptions {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            uppercase: true,
+            lowercase: true,
+            number: true,
+            symbol: true,
+            charset: None,
+            exclude: String::new(),
+            exclude_ambiguous: false,
+            min_upper: None,
+            min_lower: None,
+            min_digits: None,
+            min_symbols: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Upper,
+    Lower,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    const ALL: [CharClass; 4] = [
+        CharClass::Upper,
+        CharClass::Lower,
+        CharClass::Digit,
+        CharClass::Symbol,
+    ];
+
+    fn of(c: u8) -> Self {
+        match c {
+            b'A'..=b'Z' => CharClass::Upper,
+            b'a'..=b'z' => CharClass::Lower,
+            b'0'..=b'9' => CharClass::Digit,
+            _ => CharClass::Symbol,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Upper => "uppercase letters",
+            CharClass::Lower => "lowercase letters",
+            CharClass::Digit => "digits",
+            CharClass::Symbol => "symbols",
+        }
+    }
+}
+
+impl GenPassOptions {
+    fn class(&self, class: CharClass) -> (bool, &'static [u8], Option<usize>) {
+        match class {
+            CharClass::Upper => (self.uppercase, UPPER, self.min_upper),
+            CharClass::Lower => (self.lowercase, LOWER, self.min_lower),
+            CharClass::Digit => (self.number, NUMBER, self.min_digits),
+            CharClass::Symbol => (self.symbol, SYMBOL, self.min_symbols),
+        }
+    }
+}
+
+pub fn process_genpass(options: &GenPassOptions) -> anyhow::Result<Vec<u8>> {
     let mut rng = rand::thread_rng();
-    let mut password = vec![];
-    let mut chars = vec![];
-
-    if uppercase {
-        chars.extend_from_slice(UPPER);
-        password.push(
-            *UPPER
-                .choose(&mut rng)
-                .expect("UPPER won'ts be empty in this context"),
-        );
+
+    if options.length == 0 {
+        anyhow::bail!("The password length must be at least 1");
     }
-    if lowercase {
-        chars.extend_from_slice(LOWER);
-        password.push(
-            *LOWER
-                .choose(&mut rng)
-                .expect("LOWER won'ts be empty in this context"),
-        );
+    if options.charset.is_none()
+        && !(options.uppercase || options.lowercase || options.number || options.symbol)
+    {
+        anyhow::bail!("At least one character class must be enabled");
     }
-    if number {
-        chars.extend_from_slice(NUMBER);
-        password.push(
-            *NUMBER
-                .choose(&mut rng)
-                .expect("NUMBER won'ts be empty in this context"),
-        );
+    let charset = match &options.charset {
+        Some(charset) => {
+            if !charset.bytes().all(|c| c.is_ascii_graphic()) {
+                anyhow::bail!("The charset must only have printable ascii characters");
+            }
+            Some(charset.as_bytes())
+        }
+        None => None,
+    };
+    let excluded = |c: &u8| {
+        options.exclude.as_bytes().contains(c) || options.exclude_ambiguous && AMBIGUOUS.contains(c)
+    };
+
+    // characters of every used class, with how many of them the password needs
+    let mut classes = vec![];
+    for class in CharClass::ALL {
+        let (enabled, defaults, min) = options.class(class);
+        let (enabled, chars) = match charset {
+            Some(charset) => {
+                let chars = charset
+                    .iter()
+                    .filter(|&&c| CharClass::of(c) == class)
+                    .collect::<Vec<_>>();
+                (!chars.is_empty(), chars)
+            }
+            None => (enabled, defaults.iter().collect()),
+        };
+        if !enabled {
+            if min.unwrap_or(0) > 0 {
+                anyhow::bail!("Cannot require {} without using them", class.name());
+            }
+            continue;
+        }
+        let mut chars = chars
+            .into_iter()
+            .filter(|c| !excluded(c))
+            .copied()
+            .collect::<Vec<_>>();
+        chars.sort_unstable();
+        chars.dedup();
+        let min = min.unwrap_or(1);
+        if chars.is_empty() && min > 0 {
+            anyhow::bail!("No {} are left after the exclusions", class.name());
+        }
+        classes.push((chars, min));
+    }
+
+    let chars = classes
+        .iter()
+        .flat_map(|(chars, _)| chars)
+        .copied()
+        .collect::<Vec<_>>();
+    if chars.is_empty() {
+        anyhow::bail!("No characters are left to generate a password from");
     }
-    if symbol {
-        chars.extend_from_slice(SYMBOL);
-        password.push(
-            *SYMBOL
-                .choose(&mut rng)
-                .expect("SYMBOL won'ts be empty in this context"),
+    let required = classes.iter().map(|(_, min)| min).sum::<usize>();
+    if required > options.length {
+        anyhow::bail!(
+            "A password of {} characters cannot hold the {} required ones",
+            options.length,
+            required
         );
     }
 
-    for _ in 0..(length - password.len()) {
-        let c = chars
-            .choose(&mut rng)
-            .expect("chars won'ts be empty in this context");
-        password.push(*c);
+    let mut password = vec![];
+    for (class, min) in &classes {
+        for _ in 0..*min {
+            password.push(*class.choose(&mut rng).expect("checked to be non-empty"));
+        }
+    }
+    while password.len() < options.length {
+        password.push(*chars.choose(&mut rng).expect("checked to be non-empty"));
     }
 
     password.shuffle(&mut rng);
     Ok(password)
 }
+
+/// zxcvbn's estimate of how hard a password is to guess
+#[derive(Debug, Serialize)]
+pub struct PasswordStrength {
+    /// 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimes,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+/// Time to crack a password in a few attack scenarios
+#[derive(Debug, Serialize)]
+pub struct CrackTimes {
+    pub online_throttling_100_per_hour: CrackTime,
+    pub online_no_throttling_10_per_second: CrackTime,
+    pub offline_slow_hashing_1e4_per_second: CrackTime,
+    pub offline_fast_hashing_1e10_per_second: CrackTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrackTime {
+    pub seconds: f64,
+    /// e.g. `3 hours` or `centuries`
+    pub display: String,
+}
+
+impl From<CrackTimeSeconds> for CrackTime {
+    fn from(time: CrackTimeSeconds) -> Self {
+        let seconds = match time {
+            CrackTimeSeconds::Integer(seconds) => seconds as f64,
+            CrackTimeSeconds::Float(seconds) => seconds,
+        };
+        Self {
+            seconds,
+            display: time.to_string(),
+        }
+    }
+}
+
+pub fn password_strength(password: &str) -> anyhow::Result<PasswordStrength> {
+    let estimate = zxcvbn(password, &[])?;
+    let times = estimate.crack_times();
+    let (warning, suggestions) = match estimate.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|w| w.to_string()),
+            feedback
+                .suggestions()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        None => (None, vec![]),
+    };
+    Ok(PasswordStrength {
+        score: estimate.score(),
+        guesses: estimate.guesses(),
+        guesses_log10: estimate.guesses_log10(),
+        crack_times: CrackTimes {
+            online_throttling_100_per_hour: times.online_throttling_100_per_hour().into(),
+            online_no_throttling_10_per_second: times.online_no_throttling_10_per_second().into(),
+            offline_slow_hashing_1e4_per_second: times.offline_slow_hashing_1e4_per_second().into(),
+            offline_fast_hashing_1e10_per_second: times
+                .offline_fast_hashing_1e10_per_second()
+                .into(),
+        },
+        warning,
+        suggestions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_genpass_charset() -> anyhow::Result<()> {
+        let options = GenPassOptions {
+            length: 12,
+            exclude: "!@#".to_string(),
+            exclude_ambiguous: true,
+            min_digits: Some(3),
+            ..Default::default()
+        };
+        let password = process_genpass(&options)?;
+        assert_eq!(password.len(), 12);
+        assert!(password.iter().filter(|c| c.is_ascii_digit()).count() >= 3);
+        assert!(!password.iter().any(|c| b"!@#0Oo1lI|".contains(c)));
+
+        let options = GenPassOptions {
+            charset: Some("abc123".to_string()),
+            min_digits: Some(4),
+            ..Default::default()
+        };
+        let password = process_genpass(&options)?;
+        assert!(password.iter().all(|c| b"abc123".contains(c)));
+        assert!(password.iter().filter(|c| c.is_ascii_digit()).count() >= 4);
+
+        let invalid = [
+            GenPassOptions {
+                charset: Some("abc".to_string()),
+                min_symbols: Some(1),
+                ..Default::default()
+            },
+            GenPassOptions {
+                exclude: SYMBOL.iter().map(|&c| c as char).collect(),
+                ..Default::default()
+            },
+            GenPassOptions {
+                length: 4,
+                min_digits: Some(2),
+                ..Default::default()
+            },
+        ];
+        for options in &invalid {
+            assert!(process_genpass(options).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_invalid_length_and_classes() {
+        let options = GenPassOptions {
+            uppercase: false,
+            lowercase: false,
+            number: false,
+            symbol: false,
+            ..Default::default()
+        };
+        assert!(process_genpass(&options).is_err());
+        for length in 0..4 {
+            let options = GenPassOptions {
+                length,
+                ..Default::default()
+            };
+            assert!(process_genpass(&options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_password_strength() -> anyhow::Result<()> {
+        let weak = password_strength("password")?;
+        assert_eq!(weak.score, 0);
+        assert!(weak.warning.is_some());
+        assert!(!weak.suggestions.is_empty());
+
+        let strong = password_strength("k9$Vq2_xLm#4Tz8w")?;
+        assert_eq!(strong.score, 4);
+        assert!(strong.guesses > weak.guesses);
+        assert_eq!(
+            strong
+                .crack_times
+                .offline_slow_hashing_1e4_per_second
+                .display,
+            "centuries"
+        );
+        assert!(password_strength("").is_err());
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_genpass_classes(
+            length in 1usize..64,
+            uppercase: bool,
+            lowercase: bool,
+            number: bool,
+            symbol: bool,
+        ) {
+            let options = GenPassOptions {
+                length,
+                uppercase,
+                lowercase,
+                number,
+                symbol,
+                ..Default::default()
+            };
+            let enabled = [(uppercase, UPPER), (lowercase, LOWER), (number, NUMBER), (symbol, SYMBOL)];
+            let classes = enabled.iter().filter(|(on, _)| *on).count();
+            match process_genpass(&options) {
+                Ok(password) => {
+                    prop_assert_eq!(password.len(), length);
+                    for (on, chars) in enabled {
+                        let count = password.iter().filter(|c| chars.contains(c)).count();
+                        if on {
+                            prop_assert!(count >= 1);
+                        } else {
+                            prop_assert_eq!(count, 0);
+                        }
+                    }
+                }
+                Err(_) => prop_assert!(classes == 0 || classes > length),
+            }
+        }
+
+        #[test]
+        fn prop_genpass_minimums(
+            length in 1usize..64,
+            min_digits in 0usize..8,
+            min_symbols in 0usize..8,
+            exclude_ambiguous: bool,
+        ) {
+            let options = GenPassOptions {
+                length,
+                exclude: "#$".to_string(),
+                exclude_ambiguous,
+                min_digits: Some(min_digits),
+                min_symbols: Some(min_symbols),
+                ..Default::default()
+            };
+            match process_genpass(&options) {
+                Ok(password) => {
+                    prop_assert_eq!(password.len(), length);
+                    let digits = password.iter().filter(|c| c.is_ascii_digit()).count();
+                    let symbols = password.iter().filter(|c| SYMBOL.contains(c)).count();
+                    prop_assert!(digits >= min_digits);
+                    prop_assert!(symbols >= min_symbols);
+                    prop_assert!(!password.iter().any(|c| b"#$".contains(c)));
+                    if exclude_ambiguous {
+                        prop_assert!(!password.iter().any(|c| AMBIGUOUS.contains(c)));
+                    }
+                }
+                Err(_) => prop_assert!(2 + min_digits + min_symbols > length),
+            }
+        }
+    }
+}
@@ -51,8 +51,6 @@ pub fn process_jwt_verify(token: String, aud: String) -> anyhow::Result<bool> {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_jwt() {
         use jsonwebtoken::errors::ErrorKind;
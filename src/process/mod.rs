@@ -1,15 +1,62 @@
 mod b64;
 mod chacha20poly1305;
+mod csv_anonymize;
+mod csv_arrow;
+mod csv_batch;
+mod csv_binary;
+mod csv_cat;
 mod csv_convert;
+mod csv_dedup;
+mod csv_diff;
+mod csv_encoding;
+mod csv_fake;
+mod csv_filter;
+mod csv_from;
+mod csv_group;
+mod csv_join;
+mod csv_markup;
+mod csv_query;
+mod csv_sample;
+mod csv_show;
+mod csv_split;
+mod csv_sqlite;
+mod csv_stats;
+mod csv_types;
+mod csv_validate;
+mod csv_writer;
 mod gen_pass;
 mod http_serve;
 mod jwt;
+mod passphrase;
 mod text;
 
 pub use b64::*;
 pub use chacha20poly1305::*;
+pub use csv_anonymize::*;
+pub use csv_arrow::*;
+pub use csv_batch::*;
+pub use csv_binary::*;
+pub use csv_cat::*;
 pub use csv_convert::*;
+pub use csv_dedup::*;
+pub use csv_diff::*;
+pub use csv_encoding::*;
+pub use csv_fake::*;
+pub use csv_filter::*;
+pub use csv_from::*;
+pub use csv_group::*;
+pub use csv_join::*;
+pub use csv_query::*;
+pub use csv_sample::*;
+pub use csv_show::*;
+pub use csv_split::*;
+pub use csv_sqlite::*;
+pub use csv_stats::*;
+pub use csv_types::*;
+pub use csv_validate::*;
+pub use csv_writer::*;
 pub use gen_pass::*;
 pub use http_serve::*;
 pub use jwt::*;
+pub use passphrase::*;
 pub use text::*;
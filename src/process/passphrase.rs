@@ -0,0 +1,182 @@
+use crate::process::gen_pass::{NUMBER, SYMBOL};
+use crate::utils::get_data;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashSet;
+use std::io::Read;
+
+/// Words a passphrase is drawn from
+#[derive(Debug, Clone)]
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let reader = get_data(path).map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot read wordlist {}: {}. Download the EFF large wordlist from \
+                 https://www.eff.org/files/2016/07/18/eff_large_wordlist.txt or pass --wordlist",
+                path,
+                e
+            )
+        })?;
+        Self::from_reader(reader)
+    }
+
+    /// One word per line, optionally preceded by its dice roll like `11111\tabacus`.
+    /// Duplicates are dropped so they don't inflate the entropy.
+    pub fn from_reader(mut reader: impl Read) -> anyhow::Result<Self> {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        let mut seen = HashSet::new();
+        let words = data
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next()) {
+                    (Some(roll), Some(word))
+                        if roll.bytes().all(|b| (b'1'..=b'6').contains(&b)) =>
+                    {
+                        Some(word)
+                    }
+                    (Some(word), None) => Some(word),
+                    _ => None,
+                }
+            })
+            .filter(|word| seen.insert(*word))
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if words.len() < 2 {
+            anyhow::bail!("A wordlist needs at least two distinct words");
+        }
+        Ok(Self { words })
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// How the words of a passphrase are put together
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    /// Upper case the first letter of every word
+    pub capitalize: bool,
+    /// Append a random digit to a random word
+    pub digit: bool,
+    /// Append a random symbol to a random word
+    pub symbol: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Passphrase {
+    pub phrase: String,
+    /// Bits of entropy, assuming the attacker knows the wordlist and the options
+    pub entropy: f64,
+}
+
+pub fn process_passphrase(
+    wordlist: &Wordlist,
+    options: &PassphraseOptions,
+    rng: &mut impl Rng,
+) -> anyhow::Result<Passphrase> {
+    if options.words == 0 {
+        anyhow::bail!("A passphrase needs at least one word");
+    }
+    let mut words = (0..options.words)
+        .map(|_| {
+            let word = wordlist
+                .words
+                .choose(rng)
+                .expect("wordlists have at least two words");
+            if options.capitalize {
+                capitalize(word)
+            } else {
+                word.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let count = options.words as f64;
+    let mut entropy = count * (wordlist.len() as f64).log2();
+    for (enabled, chars) in [(options.digit, NUMBER), (options.symbol, SYMBOL)] {
+        if enabled {
+            let c = *chars.choose(rng).expect("character sets aren't empty");
+            words[rng.gen_range(0..options.words)].push(c as char);
+            entropy += (chars.len() as f64).log2() + count.log2();
+        }
+    }
+    Ok(Passphrase {
+        phrase: words.join(&options.separator),
+        entropy,
+    })
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn options() -> PassphraseOptions {
+        PassphraseOptions {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            digit: false,
+            symbol: false,
+        }
+    }
+
+    #[test]
+    fn test_wordlist() -> anyhow::Result<()> {
+        let wordlist = Wordlist::load("fixture/wordlist.txt")?;
+        assert_eq!(wordlist.len(), 6);
+        let plain = Wordlist::from_reader("alpha\nbravo\n\nalpha\n".as_bytes())?;
+        assert_eq!(plain.words, vec!["alpha", "bravo"]);
+        assert!(Wordlist::from_reader("alpha\n".as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase() -> anyhow::Result<()> {
+        let wordlist = Wordlist::load("fixture/wordlist.txt")?;
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let passphrase = process_passphrase(&wordlist, &options(), &mut rng)?;
+        let words = passphrase.phrase.split('-').collect::<Vec<_>>();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| wordlist.words.iter().any(|x| x == w)));
+        assert!((passphrase.entropy - 6.0 * 6f64.log2()).abs() < 1e-9);
+
+        let options = PassphraseOptions {
+            separator: " ".to_string(),
+            capitalize: true,
+            digit: true,
+            symbol: true,
+            ..options()
+        };
+        let passphrase = process_passphrase(&wordlist, &options, &mut rng)?;
+        assert_eq!(passphrase.phrase.split(' ').count(), 6);
+        assert!(passphrase.phrase.split(' ').all(|w| w.starts_with('A')));
+        assert!(passphrase.phrase.bytes().any(|b| b.is_ascii_digit()));
+        assert!(passphrase.phrase.bytes().any(|b| SYMBOL.contains(&b)));
+        assert!(passphrase.entropy > 6.0 * 6f64.log2() + 10f64.log2());
+        Ok(())
+    }
+}
@@ -21,7 +21,7 @@ trait TextVerify {
     fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> anyhow::Result<bool>;
 }
 
-trait TextLoader {
+pub(crate) trait TextLoader {
     fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
     where
         Self: Sized;
@@ -33,7 +33,20 @@ trait KeyGenerator {
         Self: Sized;
 }
 
-struct Blake3 {
+/// The 32 key bytes at the start of a key file
+fn key_prefix(key: &[u8]) -> anyhow::Result<&[u8; 32]> {
+    match key.get(..32) {
+        Some(key) => Ok(key.try_into()?),
+        None => anyhow::bail!("key is {} bytes long, at least 32 are needed", key.len()),
+    }
+}
+
+fn load_key<T>(path: &Path, try_new: impl Fn(&[u8]) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let key = fs::read(path)?;
+    try_new(&key).map_err(|e| anyhow::anyhow!("Invalid key file {}: {}", path.display(), e))
+}
+
+pub(crate) struct Blake3 {
     key: [u8; 32],
 }
 
@@ -43,11 +56,15 @@ impl Blake3 {
     }
 
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
-        let blake3 = Self::new(key);
+        let key = key_prefix(key)?;
+        let blake3 = Self::new(*key);
         Ok(blake3)
     }
+
+    /// Keyed digest of `data`
+    pub fn hash(&self, data: &[u8]) -> blake3::Hash {
+        blake3::keyed_hash(&self.key, data)
+    }
 }
 
 impl TextLoader for Blake3 {
@@ -55,8 +72,7 @@ impl TextLoader for Blake3 {
     where
         Self: Sized,
     {
-        let key = fs::read(path)?;
-        Self::try_new(&key)
+        load_key(path.as_ref(), Self::try_new)
     }
 }
 
@@ -65,7 +81,10 @@ impl KeyGenerator for Blake3 {
     where
         Self: Sized,
     {
-        let key = crate::process::process_genpass(32, true, true, true, true)?;
+        let key = crate::process::process_genpass(&crate::process::GenPassOptions {
+            length: 32,
+            ..Default::default()
+        })?;
         Ok(vec![key.to_vec()])
     }
 }
@@ -80,8 +99,7 @@ impl Ed25519Signer {
     }
 
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
+        let key = key_prefix(key)?;
         let key = SigningKey::from_bytes(key);
         let ed25519_dalek = Self::new(key);
         Ok(ed25519_dalek)
@@ -93,8 +111,7 @@ impl TextLoader for Ed25519Signer {
     where
         Self: Sized,
     {
-        let key = fs::read(path)?;
-        Self::try_new(&key)
+        load_key(path.as_ref(), Self::try_new)
     }
 }
 
@@ -120,8 +137,7 @@ impl Ed25519Verifier {
     }
 
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = &key[..32];
-        let key = key.try_into().unwrap();
+        let key = key_prefix(key)?;
         let key = VerifyingKey::from_bytes(key)?;
         let ed25519_dalek = Ed25519Verifier::new(key);
         Ok(ed25519_dalek)
@@ -133,8 +149,7 @@ impl TextLoader for Ed25519Verifier {
     where
         Self: Sized,
     {
-        let key = fs::read(path)?;
-        Self::try_new(&key)
+        load_key(path.as_ref(), Self::try_new)
     }
 }
 
@@ -191,7 +206,7 @@ impl TextSign for Blake3 {
         // TODO: improve perf by reading in chunks
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        Ok(blake3::keyed_hash(&self.key, &buf).as_bytes().to_vec())
+        Ok(self.hash(&buf).as_bytes().to_vec())
     }
 }
 
@@ -199,7 +214,7 @@ impl TextVerify for Blake3 {
     fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> anyhow::Result<bool> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        let hash = blake3::keyed_hash(&self.key, &buf);
+        let hash = self.hash(&buf);
         let hash = hash.as_bytes();
         Ok(hash == sig)
     }
@@ -245,4 +260,16 @@ mod tests {
         assert!(ed25519_dalek.verify(&mut &data[..], &sig)?);
         Ok(())
     }
+
+    #[test]
+    fn test_short_key() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli-short-key-{}", std::process::id()));
+        fs::write(&path, b"short")?;
+        let err = Blake3::load(&path).err().expect("a 5 byte key is rejected");
+        assert!(err.to_string().contains(&path.display().to_string()));
+        assert!(Ed25519Signer::load(&path).is_err());
+        assert!(Ed25519Verifier::load(&path).is_err());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
 }